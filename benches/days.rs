@@ -25,6 +25,40 @@ pub fn bench_day3(c: &mut Criterion) {
     c.bench_function("day 3 pt 2", |b| {
         b.iter(|| day3::calculate_slopes_cost_multiplied(&input))
     });
+
+    let mut pt2_group = c.benchmark_group("Day 3 Pt 2");
+    pt2_group.bench_function("array2d", |b| {
+        b.iter(|| day3::calculate_slopes_cost_multiplied(black_box(&input)))
+    });
+    pt2_group.bench_function("bit-packed", |b| {
+        b.iter(|| day3::calculate_slopes_cost_multiplied_packed(black_box(&input)))
+    });
+    pt2_group.finish();
+
+    // Pseudo random map way bigger than the puzzle input, ~1 tree every 4 cells.
+    let mut seed: u64 = 42;
+    let large_input: Vec<String> = (0..4096)
+        .map(|_| {
+            (0..1000)
+                .map(|_| {
+                    seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                    if seed >> 62 == 0 {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let mut large_group = c.benchmark_group("Day 3 Pt 2 - large map");
+    large_group.bench_function("array2d", |b| {
+        b.iter(|| day3::calculate_slopes_cost_multiplied(black_box(&large_input)))
+    });
+    large_group.bench_function("bit-packed", |b| {
+        b.iter(|| day3::calculate_slopes_cost_multiplied_packed(black_box(&large_input)))
+    });
+    large_group.finish();
 }
 
 pub fn bench_day4(c: &mut Criterion) {
@@ -102,6 +136,49 @@ pub fn bench_day11(c: &mut Criterion) {
     let input = input_utils::read_all("inputs/day11");
     c.bench_function("day 11 pt 1", |b| b.iter(|| day11::part1(&input)));
     c.bench_function("day 11 pt 2", |b| b.iter(|| day11::part2(&input)));
+
+    let rules = [
+        ("pt 1", day11::SeatingRules::part1()),
+        ("pt 2", day11::SeatingRules::part2()),
+    ];
+    let mut group = c.benchmark_group("Day 11");
+    for (name, rules) in rules.iter() {
+        group.bench_function(format!("{} - full scan", name), |b| {
+            b.iter(|| day11::count_occupied_when_stable_full_scan(black_box(&input), rules))
+        });
+        group.bench_function(format!("{} - incremental", name), |b| {
+            b.iter(|| day11::count_occupied_when_stable(black_box(&input), rules))
+        });
+    }
+    group.finish();
+
+    // Pseudo random seat map way bigger than the puzzle input, ~1 floor cell every 4. The adjacent
+    // rules of part 1 make seats on these maps flip forever, so only part 2 is measured.
+    let mut seed: u64 = 42;
+    let large_input: Vec<String> = (0..200)
+        .map(|_| {
+            (0..200)
+                .map(|_| {
+                    seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                    if seed >> 62 == 0 {
+                        '.'
+                    } else {
+                        'L'
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let rules = day11::SeatingRules::part2();
+    let mut large_group = c.benchmark_group("Day 11 Pt 2 - large map");
+    large_group.sample_size(10);
+    large_group.bench_function("full scan", |b| {
+        b.iter(|| day11::count_occupied_when_stable_full_scan(black_box(&large_input), &rules))
+    });
+    large_group.bench_function("incremental", |b| {
+        b.iter(|| day11::count_occupied_when_stable(black_box(&large_input), &rules))
+    });
+    large_group.finish();
 }
 
 pub fn bench_day12(c: &mut Criterion) {
@@ -0,0 +1,211 @@
+// Small arbitrary precision unsigned integer, enough to count things that don't fit in 64 bits.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul};
+
+/// Unsigned integer of any size, stored as base 2^32 digits, least significant first, without
+/// trailing zeros (so zero has no digits).
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Default)]
+pub struct BigUint {
+    digits: Vec<u32>,
+}
+
+impl BigUint {
+    pub fn zero() -> BigUint {
+        BigUint { digits: Vec::new() }
+    }
+
+    pub fn one() -> BigUint {
+        BigUint::from(1_u64)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    fn trim(mut self) -> BigUint {
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+        self
+    }
+
+    /// The value as an u64, if it fits.
+    pub fn to_u64(&self) -> Option<u64> {
+        match self.digits.len() {
+            0 => Some(0),
+            1 => Some(self.digits[0] as u64),
+            2 => Some(self.digits[0] as u64 | (self.digits[1] as u64) << 32),
+            _ => None,
+        }
+    }
+
+    /// Divides in place by a small divisor, returning the remainder.
+    fn div_rem_small(&mut self, divisor: u32) -> u32 {
+        let mut remainder = 0_u64;
+        for digit in self.digits.iter_mut().rev() {
+            let value = remainder << 32 | *digit as u64;
+            *digit = (value / divisor as u64) as u32;
+            remainder = value % divisor as u64;
+        }
+        *self = std::mem::take(self).trim();
+        remainder as u32
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(value: u64) -> BigUint {
+        BigUint {
+            digits: vec![value as u32, (value >> 32) as u32],
+        }
+        .trim()
+    }
+}
+
+impl From<usize> for BigUint {
+    fn from(value: usize) -> BigUint {
+        BigUint::from(value as u64)
+    }
+}
+
+impl Add for &BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: &BigUint) -> BigUint {
+        let len = self.digits.len().max(other.digits.len());
+        let mut digits = Vec::with_capacity(len + 1);
+        let mut carry = 0_u64;
+        for position in 0..len {
+            let a = *self.digits.get(position).unwrap_or(&0) as u64;
+            let b = *other.digits.get(position).unwrap_or(&0) as u64;
+            let sum = a + b + carry;
+            digits.push(sum as u32);
+            carry = sum >> 32;
+        }
+        digits.push(carry as u32);
+        BigUint { digits }.trim()
+    }
+}
+
+impl Add for BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: BigUint) -> BigUint {
+        &self + &other
+    }
+}
+
+impl Mul for &BigUint {
+    type Output = BigUint;
+
+    // Schoolbook multiplication, fine for the sizes we deal with.
+    fn mul(self, other: &BigUint) -> BigUint {
+        if self.is_zero() || other.is_zero() {
+            return BigUint::zero();
+        }
+        let mut digits = vec![0_u32; self.digits.len() + other.digits.len()];
+        for (i, a) in self.digits.iter().enumerate() {
+            let mut carry = 0_u64;
+            for (j, b) in other.digits.iter().enumerate() {
+                let value = *a as u64 * *b as u64 + digits[i + j] as u64 + carry;
+                digits[i + j] = value as u32;
+                carry = value >> 32;
+            }
+            digits[i + other.digits.len()] = carry as u32;
+        }
+        BigUint { digits }.trim()
+    }
+}
+
+impl Mul for BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: BigUint) -> BigUint {
+        &self * &other
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &BigUint) -> Ordering {
+        self.digits
+            .len()
+            .cmp(&other.digits.len())
+            .then_with(|| self.digits.iter().rev().cmp(other.digits.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &BigUint) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        // Peel off 9 decimal digits at a time, least significant first.
+        let mut value = self.clone();
+        let mut chunks = Vec::new();
+        while !value.is_zero() {
+            chunks.push(value.div_rem_small(1_000_000_000));
+        }
+        write!(f, "{}", chunks.pop().unwrap())?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_from_and_to_u64() {
+        assert_eq!(BigUint::from(0_u64), BigUint::zero());
+        assert_eq!(BigUint::from(0_u64).to_u64(), Some(0));
+        assert_eq!(BigUint::from(42_u64).to_u64(), Some(42));
+        assert_eq!(BigUint::from(u64::MAX).to_u64(), Some(u64::MAX));
+        let big = &BigUint::from(u64::MAX) + &BigUint::one();
+        assert_eq!(big.to_u64(), None);
+    }
+
+    #[test]
+    pub fn test_add_and_mul() {
+        let a = BigUint::from(u64::MAX);
+        let b = BigUint::from(3_u64);
+        assert_eq!((&a + &b).to_string(), "18446744073709551618");
+        assert_eq!((&a * &b).to_string(), "55340232221128654845");
+        assert_eq!(
+            (&a * &a).to_string(),
+            "340282366920938463426481119284349108225"
+        );
+        assert_eq!(&a * &BigUint::zero(), BigUint::zero());
+        assert_eq!(BigUint::zero() + BigUint::zero(), BigUint::zero());
+    }
+
+    #[test]
+    pub fn test_display() {
+        assert_eq!(BigUint::zero().to_string(), "0");
+        assert_eq!(BigUint::from(1_000_000_000_u64).to_string(), "1000000000");
+        assert_eq!(BigUint::from(1_000_000_007_u64).to_string(), "1000000007");
+        let two_to_the_100 = (0..100).fold(BigUint::one(), |value, _| &value + &value);
+        assert_eq!(
+            two_to_the_100.to_string(),
+            "1267650600228229401496703205376"
+        );
+    }
+
+    #[test]
+    pub fn test_ordering() {
+        let small = BigUint::from(u64::MAX);
+        let big = &small + &BigUint::one();
+        assert!(small < big);
+        assert!(BigUint::zero() < small);
+        assert_eq!(big.cmp(&big.clone()), Ordering::Equal);
+    }
+}
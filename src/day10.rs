@@ -103,31 +103,159 @@
 // and your device. What is the number of 1-jolt differences multiplied by the number of 3-jolt
 // differences?
 
-pub fn find_chain_of_adapters_hash(lines: &[String]) -> i64 {
-    let mut numbers: Vec<i64> = lines
-        .iter()
-        .map(|line| line.parse::<i64>().unwrap())
-        .collect();
+use std::collections::BTreeMap;
+
+/// The charging outlet, adapters and device, sorted by joltage, with the rules to connect them: an
+/// adapter takes an input from `min_gap` to `max_gap` jolts lower than its rating, and the device is
+/// rated `device_offset` jolts higher than the highest adapter.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct AdapterChain {
+    joltages: Vec<i64>,
+    min_gap: i64,
+    max_gap: i64,
+}
+
+impl AdapterChain {
+    pub fn new(adapters: &[i64], min_gap: i64, max_gap: i64, device_offset: i64) -> AdapterChain {
+        if min_gap < 0 || min_gap > max_gap {
+            panic!("Unexpected joltage gap range {}..={}", min_gap, max_gap);
+        }
+        if device_offset < 0 {
+            panic!("Unexpected device offset {}", device_offset);
+        }
+        if let Some(adapter) = adapters.iter().find(|adapter| **adapter < 0) {
+            panic!("Unexpected adapter {} below the outlet", adapter);
+        }
+        let mut joltages = vec![0];
+        joltages.extend(adapters);
+        joltages.sort_unstable();
+        joltages.push(joltages.last().unwrap() + device_offset);
+        AdapterChain {
+            joltages,
+            min_gap,
+            max_gap,
+        }
+    }
+
+    /// Gaps from 1 to 3 jolts, device 3 jolts above the highest adapter.
+    pub fn puzzle(adapters: &[i64]) -> AdapterChain {
+        AdapterChain::new(adapters, 1, 3, 3)
+    }
+
+    pub fn parse(lines: &[String], min_gap: i64, max_gap: i64, device_offset: i64) -> AdapterChain {
+        let adapters: Vec<i64> = lines
+            .iter()
+            .map(|line| {
+                line.parse::<i64>()
+                    .unwrap_or_else(|_| panic!("Unexpected joltage {}", line))
+            })
+            .collect();
+        AdapterChain::new(&adapters, min_gap, max_gap, device_offset)
+    }
+
+    /// Outlet, adapters and device joltages, sorted.
+    pub fn joltages(&self) -> &[i64] {
+        &self.joltages
+    }
 
-    let device_joltage = numbers.iter().max().unwrap() + 3;
+    pub fn adapters(&self) -> &[i64] {
+        &self.joltages[1..self.joltages.len() - 1]
+    }
+
+    pub fn device_joltage(&self) -> i64 {
+        *self.joltages.last().unwrap()
+    }
+
+    fn connects(&self, from: usize, to: usize) -> bool {
+        let gap = self.joltages[to] - self.joltages[from];
+        gap >= self.min_gap && gap <= self.max_gap
+    }
 
-    numbers.sort_unstable();
-    numbers.insert(0, 0);
-    numbers.push(device_joltage);
+    /// Positions reachable from the given one, in order.
+    fn next_positions(&self, from: usize) -> impl Iterator<Item = usize> + '_ {
+        (from + 1..self.joltages.len())
+            .take_while(move |to| self.joltages[*to] - self.joltages[from] <= self.max_gap)
+            .filter(move |to| self.connects(from, *to))
+    }
 
-    let mut diff1 = 0;
-    let mut diff3 = 0;
-    for i in 1..numbers.len() {
-        let diff = numbers[i] - numbers[i - 1];
-        match diff {
-            3 => diff3 += 1,
-            1 => diff1 += 1,
-            2 => continue,
-            _ => panic!("Unexpected joltage diff of {}", diff),
+    /// How many times each difference appears chaining every adapter in order, including
+    /// differences the adapters don't accept.
+    pub fn difference_histogram(&self) -> BTreeMap<i64, usize> {
+        let mut histogram = BTreeMap::new();
+        for pair in self.joltages.windows(2) {
+            *histogram.entry(pair[1] - pair[0]).or_insert(0) += 1;
         }
+        histogram
+    }
+
+    /// Whether every adapter can be used at once to connect the outlet to the device.
+    pub fn is_complete(&self) -> bool {
+        (1..self.joltages.len()).all(|to| self.connects(to - 1, to))
+    }
+
+    /// For each position, whether it can be reached from the outlet, and whether the device can be
+    /// reached from it.
+    fn reachability(&self) -> (Vec<bool>, Vec<bool>) {
+        let len = self.joltages.len();
+        let mut from_outlet = vec![false; len];
+        from_outlet[0] = true;
+        for from in 0..len {
+            if from_outlet[from] {
+                for to in self.next_positions(from) {
+                    from_outlet[to] = true;
+                }
+            }
+        }
+        let mut to_device = vec![false; len];
+        to_device[len - 1] = true;
+        for from in (0..len).rev() {
+            to_device[from] = to_device[from] || self.next_positions(from).any(|to| to_device[to]);
+        }
+        (from_outlet, to_device)
+    }
+
+    /// Whether the outlet can be connected to the device, with any subset of the adapters.
+    pub fn has_arrangement(&self) -> bool {
+        self.reachability().1[0]
     }
 
-    diff1 * diff3
+    /// Joltages of the adapters present in every arrangement, in order. Empty if there is no
+    /// arrangement at all.
+    pub fn mandatory_adapters(&self) -> Vec<i64> {
+        let (from_outlet, to_device) = self.reachability();
+        if !to_device[0] {
+            return Vec::new();
+        }
+        // An adapter can be skipped if some usable connection jumps over it. Mark the jumped
+        // ranges with +1 at their start and -1 past their end.
+        let len = self.joltages.len();
+        let mut skipped = vec![0_i64; len + 1];
+        for from in (0..len).filter(|from| from_outlet[*from]) {
+            for to in self.next_positions(from).filter(|to| to_device[*to]) {
+                skipped[from + 1] += 1;
+                skipped[to] -= 1;
+            }
+        }
+        let mut jumps = 0;
+        let mut mandatory = Vec::new();
+        for (position, change) in skipped.iter().enumerate().take(len - 1) {
+            jumps += change;
+            if jumps == 0 && position > 0 {
+                mandatory.push(self.joltages[position]);
+            }
+        }
+        mandatory
+    }
+}
+
+pub fn find_chain_of_adapters_hash(lines: &[String]) -> i64 {
+    let chain = AdapterChain::parse(lines, 1, 3, 3);
+    let histogram = chain.difference_histogram();
+    if !chain.is_complete() {
+        panic!("Unexpected joltage diffs {:?}", histogram);
+    }
+    let count = |difference| *histogram.get(&difference).unwrap_or(&0) as i64;
+    count(1) * count(3)
 }
 
 // --- Part Two ---
@@ -191,32 +319,324 @@ pub fn find_chain_of_adapters_hash(lines: &[String]) -> i64 {
 // What is the total number of distinct ways you can arrange the adapters to connect the charging
 // outlet to your device?
 
-pub fn count_total_combinations(lines: &[String]) -> i64 {
-    let mut adaptors: Vec<i64> = lines
-        .iter()
-        .map(|line| line.parse::<i64>().unwrap())
-        .collect();
-    adaptors.sort_unstable();
-    adaptors.insert(0, 0);
-    // Count total permutations, starting from the end until the beginning.
-    let mut permutations_to_end = vec![1; adaptors.len()];
-    for i in (0..adaptors.len()).rev() {
-        let joltage = adaptors[i];
-        // The permutations from a position to the end, is the sum of
-        // all permutations of its reachable adaptors until the end.
-        let mut acc_permutations = 0;
-        for target_i in (i + 1)..adaptors.len() {
-            let target_joltage = adaptors[target_i];
-            if target_joltage <= joltage + 3 {
-                acc_permutations += permutations_to_end[target_i];
-            } else {
-                break; // Break as soon as diff is > 3 as they are sorted.
+use crate::big_uint::BigUint;
+use std::convert::TryFrom;
+
+impl AdapterChain {
+    /// For each position, the number of ways to reach the device from it, adding counts with the
+    /// given function. None if it fails.
+    fn ways_to_device<T: Clone>(
+        &self,
+        zero: T,
+        one: T,
+        add: impl Fn(&T, &T) -> Option<T>,
+    ) -> Option<Vec<T>> {
+        let len = self.joltages.len();
+        let mut ways = vec![zero.clone(); len];
+        ways[len - 1] = one;
+        for from in (0..len - 1).rev() {
+            let mut total = zero.clone();
+            for to in self.next_positions(from) {
+                total = add(&total, &ways[to])?;
             }
+            ways[from] = total;
         }
-        if acc_permutations > 0 {
-            permutations_to_end[i] = acc_permutations;
+        Some(ways)
+    }
+
+    /// Number of arrangements, or None if it doesn't fit in an u64.
+    pub fn count_arrangements(&self) -> Option<u64> {
+        Some(self.ways_to_device(0_u64, 1, |a, b| a.checked_add(*b))?[0])
+    }
+
+    /// Number of arrangements, of any size.
+    pub fn count_arrangements_big(&self) -> BigUint {
+        let ways = self.ways_to_device(BigUint::zero(), BigUint::one(), |a, b| Some(a + b));
+        ways.unwrap().swap_remove(0)
+    }
+
+    /// Number of arrangements modulo the given prime, or any other modulus.
+    pub fn count_arrangements_mod(&self, modulus: u64) -> u64 {
+        if modulus == 0 {
+            panic!("Unexpected modulus 0");
+        }
+        let add = |a: &u64, b: &u64| Some(((*a as u128 + *b as u128) % modulus as u128) as u64);
+        self.ways_to_device(0, 1 % modulus, add).unwrap()[0]
+    }
+
+    /// Lazily enumerates the arrangements, as the joltages of the adapters used, in lexicographic
+    /// order. Only meant for small inputs, as there can be a huge number of them.
+    pub fn arrangements(&self) -> Arrangements<'_> {
+        let (_, to_device) = self.reachability();
+        Arrangements {
+            chain: self,
+            path: Vec::new(),
+            started: !to_device[0],
+            to_device,
+        }
+    }
+
+    /// The arrangement at the given index of `arrangements`, without enumerating the previous ones.
+    /// None if the index is out of range or the count doesn't fit in an u64.
+    pub fn arrangement(&self, index: u64) -> Option<Vec<i64>> {
+        let ways = self.ways_to_device(0_u64, 1, |a, b| a.checked_add(*b))?;
+        self.unrank(&ways, index)
+    }
+
+    fn unrank(&self, ways: &[u64], mut index: u64) -> Option<Vec<i64>> {
+        if index >= ways[0] {
+            return None;
+        }
+        let device = self.joltages.len() - 1;
+        let mut arrangement = Vec::new();
+        let mut position = 0;
+        while position != device {
+            for to in self.next_positions(position) {
+                if index < ways[to] {
+                    position = to;
+                    break;
+                }
+                index -= ways[to];
+            }
+            if position != device {
+                arrangement.push(self.joltages[position]);
+            }
+        }
+        Some(arrangement)
+    }
+
+    /// Endless stream of arrangements picked uniformly at random, from the given seed. None if there
+    /// is no arrangement or the count doesn't fit in an u64.
+    pub fn sample_arrangements(&self, seed: u64) -> Option<impl Iterator<Item = Vec<i64>> + '_> {
+        let ways = self.ways_to_device(0_u64, 1, |a, b| a.checked_add(*b))?;
+        let count = ways[0];
+        if count == 0 {
+            return None;
+        }
+        let mut state = seed;
+        Some(std::iter::from_fn(move || {
+            let index = random_below(&mut state, count);
+            self.unrank(&ways, index)
+        }))
+    }
+}
+
+/// SplitMix64 step, good enough to sample arrangements.
+fn next_random(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Uniform number in 0..bound, rejecting the values that would bias the remainder.
+fn random_below(state: &mut u64, bound: u64) -> u64 {
+    let zone = u64::MAX - u64::MAX % bound;
+    loop {
+        let value = next_random(state);
+        if value < zone {
+            return value % bound;
+        }
+    }
+}
+
+/// Iterator over the arrangements of an adapter chain, see `AdapterChain::arrangements`.
+pub struct Arrangements<'a> {
+    chain: &'a AdapterChain,
+    to_device: Vec<bool>,
+    /// Positions of the last arrangement, from the outlet to the device.
+    path: Vec<usize>,
+    started: bool,
+}
+
+impl Arrangements<'_> {
+    fn next_viable(&self, from: usize, after: Option<usize>) -> Option<usize> {
+        self.chain
+            .next_positions(from)
+            .filter(|to| after.is_none_or(|after| *to > after))
+            .find(|to| self.to_device[*to])
+    }
+
+    /// Extends the path with the first viable positions until the device.
+    fn descend(&mut self) -> Vec<i64> {
+        let device = self.chain.joltages.len() - 1;
+        while *self.path.last().unwrap() != device {
+            let next = self.next_viable(*self.path.last().unwrap(), None).unwrap();
+            self.path.push(next);
+        }
+        self.path[1..self.path.len() - 1]
+            .iter()
+            .map(|position| self.chain.joltages[*position])
+            .collect()
+    }
+}
+
+impl Iterator for Arrangements<'_> {
+    type Item = Vec<i64>;
+
+    fn next(&mut self) -> Option<Vec<i64>> {
+        if !self.started {
+            self.started = true;
+            self.path.push(0);
+            return Some(self.descend());
+        }
+        // Backtrack to the last position with another viable choice.
+        while let Some(last) = self.path.pop() {
+            if let Some(parent) = self.path.last() {
+                if let Some(next) = self.next_viable(*parent, Some(last)) {
+                    self.path.push(next);
+                    return Some(self.descend());
+                }
+            }
+        }
+        None
+    }
+}
+
+pub fn count_total_combinations(lines: &[String]) -> i64 {
+    let count = AdapterChain::parse(lines, 1, 3, 3).count_arrangements_big();
+    count
+        .to_u64()
+        .and_then(|count| i64::try_from(count).ok())
+        .unwrap_or_else(|| panic!("Unexpected arrangement count {} overflowing i64", count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(numbers: &[i64]) -> Vec<String> {
+        numbers.iter().map(|number| number.to_string()).collect()
+    }
+
+    const SMALL_EXAMPLE: [i64; 11] = [16, 10, 15, 5, 1, 11, 7, 19, 6, 12, 4];
+
+    const LARGE_EXAMPLE: [i64; 31] = [
+        28, 33, 18, 42, 31, 14, 46, 20, 48, 47, 24, 23, 49, 45, 19, 38, 39, 11, 1, 32, 25, 35, 8,
+        17, 7, 9, 4, 2, 34, 10, 3,
+    ];
+
+    #[test]
+    pub fn test_find_chain_of_adapters() {
+        assert_eq!(find_chain_of_adapters_hash(&lines(&SMALL_EXAMPLE)), 35);
+        assert_eq!(find_chain_of_adapters_hash(&lines(&LARGE_EXAMPLE)), 220);
+    }
+
+    #[test]
+    pub fn test_difference_histogram() {
+        let chain = AdapterChain::puzzle(&SMALL_EXAMPLE);
+        assert_eq!(chain.device_joltage(), 22);
+        assert_eq!(chain.adapters().len(), 11);
+        assert_eq!(
+            chain.difference_histogram(),
+            vec![(1, 7), (3, 5)].into_iter().collect()
+        );
+        assert!(chain.is_complete());
+        assert!(chain.has_arrangement());
+
+        let chain = AdapterChain::puzzle(&[1, 5, 6]);
+        assert_eq!(
+            chain.difference_histogram(),
+            vec![(1, 2), (3, 1), (4, 1)].into_iter().collect()
+        );
+        assert!(!chain.is_complete());
+        assert!(!chain.has_arrangement());
+        assert!(chain.mandatory_adapters().is_empty());
+    }
+
+    #[test]
+    pub fn test_mandatory_adapters() {
+        let chain = AdapterChain::puzzle(&SMALL_EXAMPLE);
+        assert_eq!(
+            chain.mandatory_adapters(),
+            vec![1, 4, 7, 10, 12, 15, 16, 19]
+        );
+
+        // With a wider tolerance only the last adapter can't be skipped.
+        let chain = AdapterChain::new(&SMALL_EXAMPLE, 1, 5, 3);
+        assert_eq!(chain.mandatory_adapters(), vec![19]);
+    }
+
+    #[test]
+    pub fn test_custom_tolerance() {
+        // Only gaps of exactly 2, so 3 and 5 are a dead end.
+        let chain = AdapterChain::new(&[2, 3, 4, 5, 6], 2, 2, 2);
+        assert_eq!(chain.device_joltage(), 8);
+        assert!(!chain.is_complete());
+        assert!(chain.has_arrangement());
+        assert_eq!(chain.mandatory_adapters(), vec![2, 4, 6]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unexpected device offset -5")]
+    pub fn test_negative_device_offset() {
+        AdapterChain::new(&[1, 2], 1, 3, -5);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unexpected adapter -2 below the outlet")]
+    pub fn test_adapter_below_outlet() {
+        AdapterChain::new(&[-2, 1], 1, 3, 3);
+    }
+
+    #[test]
+    pub fn test_count_arrangements() {
+        assert_eq!(count_total_combinations(&lines(&SMALL_EXAMPLE)), 8);
+        assert_eq!(count_total_combinations(&lines(&LARGE_EXAMPLE)), 19208);
+        let chain = AdapterChain::puzzle(&LARGE_EXAMPLE);
+        assert_eq!(chain.count_arrangements(), Some(19208));
+        assert_eq!(chain.count_arrangements_big().to_string(), "19208");
+        assert_eq!(chain.count_arrangements_mod(1_000_000_007), 19208);
+        assert_eq!(chain.count_arrangements_mod(101), 19208 % 101);
+        assert_eq!(chain.count_arrangements_mod(1), 0);
+        assert_eq!(AdapterChain::puzzle(&[1, 5]).count_arrangements(), Some(0));
+    }
+
+    #[test]
+    pub fn test_count_arrangements_big() {
+        // Every joltage from 1 to 200: tribonacci growth, far beyond an u64.
+        let adapters: Vec<i64> = (1..=200).collect();
+        let chain = AdapterChain::puzzle(&adapters);
+        assert_eq!(chain.count_arrangements(), None);
+        assert_eq!(
+            chain.count_arrangements_big().to_string(),
+            "52622583840983769603765180599790256716084480555530641"
+        );
+        assert_eq!(chain.count_arrangements_mod(1_000_000_007), 615_475_309);
+    }
+
+    #[test]
+    pub fn test_enumerate_arrangements() {
+        let chain = AdapterChain::puzzle(&SMALL_EXAMPLE);
+        let arrangements: Vec<Vec<i64>> = chain.arrangements().collect();
+        assert_eq!(arrangements.len(), 8);
+        assert_eq!(arrangements[0], vec![1, 4, 5, 6, 7, 10, 11, 12, 15, 16, 19]);
+        assert_eq!(arrangements[7], vec![1, 4, 7, 10, 12, 15, 16, 19]);
+        for (index, arrangement) in arrangements.iter().enumerate() {
+            assert_eq!(chain.arrangement(index as u64).as_ref(), Some(arrangement));
+        }
+        assert_eq!(chain.arrangement(8), None);
+
+        let chain = AdapterChain::puzzle(&[1, 5]);
+        assert_eq!(chain.arrangements().next(), None);
+        assert!(chain.sample_arrangements(7).is_none());
+    }
+
+    #[test]
+    pub fn test_sample_arrangements() {
+        let chain = AdapterChain::puzzle(&SMALL_EXAMPLE);
+        let arrangements: Vec<Vec<i64>> = chain.arrangements().collect();
+        let mut hits = vec![0; arrangements.len()];
+        for sample in chain.sample_arrangements(42).unwrap().take(8000) {
+            let index = arrangements.iter().position(|a| *a == sample).unwrap();
+            hits[index] += 1;
         }
+        // Each arrangement expects 1000 hits.
+        assert!(
+            hits.iter().all(|count| *count > 850 && *count < 1150),
+            "{:?}",
+            hits
+        );
     }
-    // The 1st position contains the total permutations.
-    permutations_to_end[0]
 }
@@ -96,6 +96,8 @@
 // How many seats end up occupied?
 
 use array2d::Array2D;
+use std::collections::HashMap;
+use std::fmt;
 
 /// None = flor, false = empty seat, true = occupied seat.
 type Seat = Option<bool>;
@@ -117,100 +119,491 @@ fn parse_map(lines: &[String]) -> Array2D<Seat> {
     Array2D::<Seat>::from_rows(&rows)
 }
 
-fn iterate_until_stable(
-    map: &Array2D<Seat>,
-    search_visible: bool,
-    threshold: i32,
-) -> Array2D<Seat> {
-    #[rustfmt::skip]
-    let directions: Vec<(i32, i32)> = vec![
-        (-1, 1), (0, 1), (1, 1),
-        (-1, 0),         (1, 0),
-        (-1,-1), (0,-1), (1,-1),
-    ];
+#[rustfmt::skip]
+const DIRECTIONS: [(i32, i32); 8] = [
+    (-1, 1), (0, 1), (1, 1),
+    (-1, 0),         (1, 0),
+    (-1,-1), (0,-1), (1,-1),
+];
 
-    let mut seat_map = map.clone();
+/// Which seats count as neighbours of a seat.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Neighbourhood {
+    /// The eight seats around.
+    Adjacent,
+    /// The first seat visible in each of the eight directions, skipping the floor, looking at
+    /// most the given distance away (or until the edge of the map).
+    LineOfSight(Option<usize>),
+    /// The seats at the given (column, row) offsets, floor or not.
+    Offsets(Vec<(i32, i32)>),
+}
 
-    let valid_x = 0..seat_map.num_columns() as i32;
-    let valid_y = 0..seat_map.num_rows() as i32;
+impl Neighbourhood {
+    /// Parses `adjacent`, `sight`, `sight=<max distance>` or `offsets=<dx>,<dy>;<dx>,<dy>...`.
+    pub fn parse(text: &str) -> Neighbourhood {
+        let parse_number = |number: &str| {
+            number
+                .trim()
+                .parse::<i32>()
+                .unwrap_or_else(|_| panic!("Unexpected number {} in {}", number, text))
+        };
+        match text.split_once('=') {
+            None if text == "adjacent" => Neighbourhood::Adjacent,
+            None if text == "sight" => Neighbourhood::LineOfSight(None),
+            Some(("sight", distance)) => {
+                let distance = parse_number(distance);
+                if distance < 1 {
+                    panic!("Unexpected distance {}", distance);
+                }
+                Neighbourhood::LineOfSight(Some(distance as usize))
+            }
+            Some(("offsets", offsets)) => Neighbourhood::Offsets(
+                offsets
+                    .split(';')
+                    .map(|offset| match offset.split_once(',') {
+                        Some((dx, dy)) => (parse_number(dx), parse_number(dy)),
+                        None => panic!("Unexpected offset {}", offset),
+                    })
+                    .collect(),
+            ),
+            _ => panic!("Unexpected neighbourhood {}", text),
+        }
+    }
+}
 
-    loop {
-        let mut changed = false;
+/// How seats change on each round: an empty seat gets occupied when at most `birth_threshold`
+/// neighbours are occupied, and an occupied seat gets empty when at least `leave_threshold`
+/// neighbours are occupied.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct SeatingRules {
+    pub neighbourhood: Neighbourhood,
+    pub birth_threshold: usize,
+    pub leave_threshold: usize,
+}
 
-        let initial_seat_map = seat_map.clone();
+impl SeatingRules {
+    pub fn part1() -> SeatingRules {
+        SeatingRules {
+            neighbourhood: Neighbourhood::Adjacent,
+            birth_threshold: 0,
+            leave_threshold: 4,
+        }
+    }
 
-        for x in 0..initial_seat_map.num_columns() {
-            'outer: for y in 0..initial_seat_map.num_rows() {
-                let occupied = if let Some(occ) = initial_seat_map[(y, x)] {
-                    occ
-                } else {
-                    continue;
-                };
+    pub fn part2() -> SeatingRules {
+        SeatingRules {
+            neighbourhood: Neighbourhood::LineOfSight(None),
+            birth_threshold: 0,
+            leave_threshold: 5,
+        }
+    }
+
+    /// `part1` or `part2`.
+    pub fn preset(name: &str) -> Option<SeatingRules> {
+        match name {
+            "part1" => Some(SeatingRules::part1()),
+            "part2" => Some(SeatingRules::part2()),
+            _ => None,
+        }
+    }
+}
 
-                let mut occupied_seats = 0;
-
-                for direction in &directions {
-                    let mut distance = 1;
-                    loop {
-                        let target_x = x as i32 + (direction.0 * distance);
-                        if !valid_x.contains(&target_x) {
-                            break;
-                        }
-
-                        let target_y = y as i32 + (direction.1 * distance);
-                        if !valid_y.contains(&target_y) {
-                            break;
-                        }
-
-                        let target_index = (target_y as usize, target_x as usize);
-
-                        let target_seat = initial_seat_map[target_index];
-
-                        if let Some(visible_seat_occupied) = target_seat {
-                            if visible_seat_occupied {
-                                occupied_seats += 1;
-
-                                if occupied && occupied_seats >= threshold {
-                                    seat_map[(y, x)] = Some(false);
-                                    changed = true;
-                                    continue 'outer;
-                                }
-                            }
-                            break;
-                        }
-
-                        if search_visible {
-                            distance += 1
-                        } else {
-                            break;
-                        }
+fn count_occupied_neighbours(
+    map: &Array2D<Seat>,
+    y: usize,
+    x: usize,
+    rules: &SeatingRules,
+) -> usize {
+    let seat_at = |x: i32, y: i32| -> Option<Seat> {
+        if x < 0 || y < 0 {
+            return None;
+        }
+        map.get(y as usize, x as usize).copied()
+    };
+    let (x, y) = (x as i32, y as i32);
+    match &rules.neighbourhood {
+        Neighbourhood::Offsets(offsets) => offsets
+            .iter()
+            .filter(|(dx, dy)| seat_at(x + dx, y + dy) == Some(Some(true)))
+            .count(),
+        Neighbourhood::Adjacent => DIRECTIONS
+            .iter()
+            .filter(|(dx, dy)| seat_at(x + dx, y + dy) == Some(Some(true)))
+            .count(),
+        Neighbourhood::LineOfSight(max_distance) => DIRECTIONS
+            .iter()
+            .filter(|(dx, dy)| {
+                let mut distance = 1;
+                while max_distance.is_none_or(|max_distance| distance <= max_distance as i32) {
+                    match seat_at(x + dx * distance, y + dy * distance) {
+                        Some(Some(occupied)) => return occupied,
+                        Some(None) => distance += 1,
+                        None => return false,
                     }
                 }
+                false
+            })
+            .count(),
+    }
+}
+
+/// Rounds simulated before giving up on the seating stabilising.
+pub const DEFAULT_ROUND_LIMIT: usize = 10_000;
+
+/// Why the seating never stops changing.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum SimulationError {
+    /// The seating after round `start` comes back every `period` rounds.
+    Cycle { start: usize, period: usize },
+    /// The seating was still changing after this many rounds.
+    RoundLimit(usize),
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SimulationError::Cycle { start, period } => write!(
+                f,
+                "seating repeats every {} rounds from round {}",
+                period, start
+            ),
+            SimulationError::RoundLimit(rounds) => {
+                write!(f, "seating still changing after {} rounds", rounds)
+            }
+        }
+    }
+}
 
-                if !occupied && occupied_seats == 0 {
+fn iterate_until_stable(
+    map: &Array2D<Seat>,
+    rules: &SeatingRules,
+    round_limit: usize,
+) -> Result<Array2D<Seat>, SimulationError> {
+    let mut seat_map = map.clone();
+    let mut seen = HashMap::new();
+    seen.insert(seat_map.as_row_major(), 0);
+    for round in 1..=round_limit {
+        let mut changed = false;
+        let initial_seat_map = seat_map.clone();
+        for x in 0..initial_seat_map.num_columns() {
+            for y in 0..initial_seat_map.num_rows() {
+                let occupied = match initial_seat_map[(y, x)] {
+                    Some(occupied) => occupied,
+                    None => continue,
+                };
+                let occupied_seats = count_occupied_neighbours(&initial_seat_map, y, x, rules);
+                if occupied && occupied_seats >= rules.leave_threshold {
+                    seat_map[(y, x)] = Some(false);
+                    changed = true;
+                } else if !occupied && occupied_seats <= rules.birth_threshold {
                     seat_map[(y, x)] = Some(true);
                     changed = true;
                 }
             }
         }
-
         if !changed {
-            break;
+            return Ok(seat_map);
+        }
+        if let Some(start) = seen.insert(seat_map.as_row_major(), round) {
+            return Err(SimulationError::Cycle {
+                start,
+                period: round - start,
+            });
         }
     }
-
-    seat_map
+    Err(SimulationError::RoundLimit(round_limit))
 }
 
-pub fn part1(lines: &[String]) -> usize {
-    let map = parse_map(lines);
-    let stabilized_map = iterate_until_stable(&map, false, 4);
-    stabilized_map
-        .elements_column_major_iter()
+fn count_occupied(map: &Array2D<Seat>) -> usize {
+    map.elements_column_major_iter()
         .filter(|seat| seat.unwrap_or(false))
         .count()
 }
 
+/// A list of seats for each seat, stored in a single vector.
+#[derive(PartialEq, Eq, Debug, Clone)]
+struct AdjacencyList {
+    /// The list of seat `i` is `seats[starts[i]..starts[i + 1]]`.
+    starts: Vec<usize>,
+    seats: Vec<usize>,
+}
+
+impl AdjacencyList {
+    fn new(lists: &[Vec<usize>]) -> AdjacencyList {
+        let mut starts = Vec::with_capacity(lists.len() + 1);
+        starts.push(0);
+        let mut seats = Vec::new();
+        for list in lists {
+            seats.extend(list);
+            starts.push(seats.len());
+        }
+        AdjacencyList { starts, seats }
+    }
+
+    fn get(&self, seat: usize) -> &[usize] {
+        &self.seats[self.starts[seat]..self.starts[seat + 1]]
+    }
+}
+
+/// Random key of a seat, the hash of a seating being the xor of the keys of its occupied seats.
+/// SplitMix64 finalizer of the seat index.
+fn seat_key(seat: usize) -> u64 {
+    let mut z = (seat as u64)
+        .wrapping_add(1)
+        .wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Seat map ready to simulate many rounds: each seat knows its neighbours, computed once, and
+/// keeps a count of the occupied ones. A round only looks at the seats in the frontier, those which
+/// changed or had a neighbour change in the previous round, as no other seat can change.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct SeatSimulation {
+    rules: SeatingRules,
+    /// Seat index of each cell, row major, None for the floor.
+    cells: Array2D<Option<usize>>,
+    neighbours: AdjacencyList,
+    /// Seats having each seat as neighbour. Same as `neighbours` unless the offsets are asymmetric.
+    watchers: AdjacencyList,
+    occupied: Vec<bool>,
+    occupied_neighbours: Vec<usize>,
+    frontier: Vec<usize>,
+    in_frontier: Vec<bool>,
+    generation: usize,
+    /// Hash of the occupied seats, updated as they change.
+    hash: u64,
+}
+
+impl SeatSimulation {
+    pub fn new(lines: &[String], rules: &SeatingRules) -> SeatSimulation {
+        let map = parse_map(lines);
+        let mut occupied = Vec::new();
+        let mut cells = Array2D::filled_with(None, map.num_rows(), map.num_columns());
+        for y in 0..map.num_rows() {
+            for x in 0..map.num_columns() {
+                if let Some(seat) = map[(y, x)] {
+                    cells[(y, x)] = Some(occupied.len());
+                    occupied.push(seat);
+                }
+            }
+        }
+        let seat_at = |x: i32, y: i32| -> Option<Option<usize>> {
+            if x < 0 || y < 0 {
+                return None;
+            }
+            cells.get(y as usize, x as usize).copied()
+        };
+        let mut neighbours = vec![Vec::new(); occupied.len()];
+        for y in 0..map.num_rows() {
+            for x in 0..map.num_columns() {
+                let seat = match cells[(y, x)] {
+                    Some(seat) => seat,
+                    None => continue,
+                };
+                let (x, y) = (x as i32, y as i32);
+                neighbours[seat] = match &rules.neighbourhood {
+                    Neighbourhood::Offsets(offsets) => offsets
+                        .iter()
+                        .filter_map(|(dx, dy)| seat_at(x + dx, y + dy).flatten())
+                        .collect(),
+                    Neighbourhood::Adjacent => DIRECTIONS
+                        .iter()
+                        .filter_map(|(dx, dy)| seat_at(x + dx, y + dy).flatten())
+                        .collect(),
+                    Neighbourhood::LineOfSight(max_distance) => DIRECTIONS
+                        .iter()
+                        .filter_map(|(dx, dy)| {
+                            (1..)
+                                .take_while(|distance| {
+                                    max_distance.is_none_or(|max| *distance <= max as i32)
+                                })
+                                .map(|distance| seat_at(x + dx * distance, y + dy * distance))
+                                .take_while(|cell| cell.is_some())
+                                .find_map(|cell| cell.flatten())
+                        })
+                        .collect(),
+                };
+            }
+        }
+        let mut watchers = vec![Vec::new(); occupied.len()];
+        for (seat, seat_neighbours) in neighbours.iter().enumerate() {
+            for neighbour in seat_neighbours {
+                watchers[*neighbour].push(seat);
+            }
+        }
+        let occupied_neighbours = neighbours
+            .iter()
+            .map(|seat_neighbours| seat_neighbours.iter().filter(|n| occupied[**n]).count())
+            .collect();
+        let hash = (0..occupied.len())
+            .filter(|seat| occupied[*seat])
+            .fold(0, |hash, seat| hash ^ seat_key(seat));
+        SeatSimulation {
+            rules: rules.clone(),
+            cells,
+            frontier: (0..occupied.len()).collect(),
+            in_frontier: vec![true; occupied.len()],
+            neighbours: AdjacencyList::new(&neighbours),
+            watchers: AdjacencyList::new(&watchers),
+            occupied,
+            occupied_neighbours,
+            generation: 0,
+            hash,
+        }
+    }
+
+    /// Rounds simulated so far.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn count_occupied(&self) -> usize {
+        self.occupied.iter().filter(|occupied| **occupied).count()
+    }
+
+    /// Indices of the seats seen as neighbours from the seat at the given row and column.
+    pub fn neighbours(&self, row: usize, column: usize) -> Option<&[usize]> {
+        let seat = self.cells.get(row, column).copied().flatten()?;
+        Some(self.neighbours.get(seat))
+    }
+
+    fn changes(&self, seat: usize) -> bool {
+        let occupied_neighbours = self.occupied_neighbours[seat];
+        if self.occupied[seat] {
+            occupied_neighbours >= self.rules.leave_threshold
+        } else {
+            occupied_neighbours <= self.rules.birth_threshold
+        }
+    }
+
+    fn push_frontier(&mut self, seat: usize) {
+        if !self.in_frontier[seat] {
+            self.in_frontier[seat] = true;
+            self.frontier.push(seat);
+        }
+    }
+
+    /// Simulates a round, returning how many seats changed.
+    pub fn step(&mut self) -> usize {
+        let frontier = std::mem::take(&mut self.frontier);
+        for seat in &frontier {
+            self.in_frontier[*seat] = false;
+        }
+        let changed: Vec<usize> = frontier
+            .into_iter()
+            .filter(|seat| self.changes(*seat))
+            .collect();
+        for seat in &changed {
+            let seat = *seat;
+            let occupied = !self.occupied[seat];
+            self.occupied[seat] = occupied;
+            self.hash ^= seat_key(seat);
+            for watcher in self.watchers.get(seat) {
+                if occupied {
+                    self.occupied_neighbours[*watcher] += 1;
+                } else {
+                    self.occupied_neighbours[*watcher] -= 1;
+                }
+                if !self.in_frontier[*watcher] {
+                    self.in_frontier[*watcher] = true;
+                    self.frontier.push(*watcher);
+                }
+            }
+            self.push_frontier(seat);
+        }
+        self.generation += 1;
+        changed.len()
+    }
+
+    /// Simulates rounds until nothing changes, returning the last round that changed a seat.
+    /// Gives up after `round_limit` rounds, or as soon as a seating repeats, as it would then
+    /// repeat forever. Seatings are looked up by hash, and a match is confirmed by simulating again
+    /// up to the earlier round, so a hash collision can't report a wrong cycle.
+    pub fn run_until_stable(&mut self, round_limit: usize) -> Result<usize, SimulationError> {
+        let initial = self.clone();
+        let mut seen: HashMap<u64, Vec<usize>> = HashMap::new();
+        seen.insert(self.hash, vec![self.generation]);
+        for _ in 0..round_limit {
+            if self.step() == 0 {
+                return Ok(self.generation - 1);
+            }
+            let earlier = seen.entry(self.hash).or_default();
+            if let Some(start) = earlier
+                .iter()
+                .copied()
+                .find(|generation| initial.occupied_at(*generation) == self.occupied)
+            {
+                return Err(SimulationError::Cycle {
+                    start,
+                    period: self.generation - start,
+                });
+            }
+            earlier.push(self.generation);
+        }
+        Err(SimulationError::RoundLimit(round_limit))
+    }
+
+    /// Occupied seats at the given round, not before the current one.
+    fn occupied_at(&self, generation: usize) -> Vec<bool> {
+        let mut simulation = self.clone();
+        while simulation.generation < generation {
+            simulation.step();
+        }
+        simulation.occupied
+    }
+
+    /// The seat map in the input format.
+    pub fn render(&self) -> Vec<String> {
+        self.cells
+            .rows_iter()
+            .map(|row| {
+                row.map(|cell| match cell {
+                    None => '.',
+                    Some(seat) if self.occupied[*seat] => '#',
+                    Some(_) => 'L',
+                })
+                .collect()
+            })
+            .collect()
+    }
+}
+
+/// Occupied seats once the seating stops changing with the given rules.
+pub fn count_occupied_when_stable(
+    lines: &[String],
+    rules: &SeatingRules,
+) -> Result<usize, SimulationError> {
+    let mut simulation = SeatSimulation::new(lines, rules);
+    simulation.run_until_stable(DEFAULT_ROUND_LIMIT)?;
+    Ok(simulation.count_occupied())
+}
+
+/// Same as `count_occupied_when_stable`, rescanning every seat and its neighbourhood on each round.
+pub fn count_occupied_when_stable_full_scan(
+    lines: &[String],
+    rules: &SeatingRules,
+) -> Result<usize, SimulationError> {
+    let map = iterate_until_stable(&parse_map(lines), rules, DEFAULT_ROUND_LIMIT)?;
+    Ok(count_occupied(&map))
+}
+
+/// The seat map, in the input format, once the seating stops changing with the given rules.
+pub fn render_when_stable(
+    lines: &[String],
+    rules: &SeatingRules,
+) -> Result<Vec<String>, SimulationError> {
+    let mut simulation = SeatSimulation::new(lines, rules);
+    simulation.run_until_stable(DEFAULT_ROUND_LIMIT)?;
+    Ok(simulation.render())
+}
+
+pub fn part1(lines: &[String]) -> usize {
+    count_occupied_when_stable(lines, &SeatingRules::part1())
+        .unwrap_or_else(|error| panic!("Unexpected seating: {}", error))
+}
+
 // --- Part Two ---
 // As soon as people start to arrive, you realize your mistake. People don't just care about
 // adjacent seats - they care about the first seat they can see in each of those eight directions!
@@ -327,10 +720,195 @@ pub fn part1(lines: &[String]) -> usize {
 // equilibrium is reached, how many seats end up occupied?
 
 pub fn part2(lines: &[String]) -> usize {
-    let map = parse_map(lines);
-    let stabilized_map = iterate_until_stable(&map, true, 5);
-    stabilized_map
-        .elements_column_major_iter()
-        .filter(|seat| seat.unwrap_or(false))
-        .count()
+    count_occupied_when_stable(lines, &SeatingRules::part2())
+        .unwrap_or_else(|error| panic!("Unexpected seating: {}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_map() -> Vec<String> {
+        let input_text = "L.LL.LL.LL\nLLLLLLL.LL\nL.L.L..L..\nLLLL.LL.LL\nL.LL.LL.LL\n\
+            L.LLLLL.LL\n..L.L.....\nLLLLLLLLLL\nL.LLLLLL.L\nL.LLLLL.LL";
+        input_text.lines().map(|line| line.to_string()).collect()
+    }
+
+    fn render_map(map: &Array2D<Seat>) -> Vec<String> {
+        map.rows_iter()
+            .map(|row| {
+                row.map(|seat| match seat {
+                    None => '.',
+                    Some(true) => '#',
+                    Some(false) => 'L',
+                })
+                .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    pub fn test_presets() {
+        assert_eq!(part1(&example_map()), 37);
+        assert_eq!(part2(&example_map()), 26);
+        assert_eq!(SeatingRules::preset("part2"), Some(SeatingRules::part2()));
+        assert_eq!(SeatingRules::preset("part3"), None);
+        assert_eq!(
+            render_when_stable(&example_map(), &SeatingRules::part1()).unwrap()[0],
+            "#.#L.L#.##"
+        );
+    }
+
+    #[test]
+    pub fn test_parse_neighbourhood() {
+        assert_eq!(Neighbourhood::parse("adjacent"), Neighbourhood::Adjacent);
+        assert_eq!(
+            Neighbourhood::parse("sight"),
+            Neighbourhood::LineOfSight(None)
+        );
+        assert_eq!(
+            Neighbourhood::parse("sight=3"),
+            Neighbourhood::LineOfSight(Some(3))
+        );
+        assert_eq!(
+            Neighbourhood::parse("offsets=-1,0;1,0;0,-2"),
+            Neighbourhood::Offsets(vec![(-1, 0), (1, 0), (0, -2)])
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Unexpected neighbourhood around")]
+    pub fn test_parse_unknown_neighbourhood() {
+        Neighbourhood::parse("around");
+    }
+
+    #[test]
+    pub fn test_custom_rules() {
+        // Line of sight of distance 1 only differs from adjacent in where it stops looking.
+        let sight_one = SeatingRules {
+            neighbourhood: Neighbourhood::LineOfSight(Some(1)),
+            ..SeatingRules::part1()
+        };
+        assert_eq!(
+            count_occupied_when_stable(&example_map(), &sight_one),
+            Ok(37)
+        );
+
+        let offsets = SeatingRules {
+            neighbourhood: Neighbourhood::parse("offsets=-1,0;1,0;0,-1;0,1;-1,-1;1,-1;-1,1;1,1"),
+            ..SeatingRules::part1()
+        };
+        assert_eq!(count_occupied_when_stable(&example_map(), &offsets), Ok(37));
+
+        // A row where seats only look left and right: every other seat ends up occupied.
+        let row = vec!["LLLLL".to_string()];
+        let rules = SeatingRules {
+            neighbourhood: Neighbourhood::parse("offsets=-1,0;1,0"),
+            birth_threshold: 0,
+            leave_threshold: 2,
+        };
+        assert_eq!(
+            render_when_stable(&row, &rules),
+            Ok(vec!["#L#L#".to_string()])
+        );
+    }
+
+    #[test]
+    pub fn test_simulation_matches_full_scan() {
+        let rules = [
+            SeatingRules::part1(),
+            SeatingRules::part2(),
+            SeatingRules {
+                neighbourhood: Neighbourhood::LineOfSight(Some(2)),
+                birth_threshold: 0,
+                leave_threshold: 4,
+            },
+            SeatingRules {
+                neighbourhood: Neighbourhood::parse("offsets=1,0;0,1;2,2"),
+                birth_threshold: 1,
+                leave_threshold: 2,
+            },
+        ];
+        for rules in rules.iter() {
+            assert_eq!(
+                count_occupied_when_stable(&example_map(), rules),
+                count_occupied_when_stable_full_scan(&example_map(), rules)
+            );
+            let mut simulation = SeatSimulation::new(&example_map(), rules);
+            simulation.run_until_stable(DEFAULT_ROUND_LIMIT).unwrap();
+            let map = iterate_until_stable(&parse_map(&example_map()), rules, DEFAULT_ROUND_LIMIT);
+            assert_eq!(simulation.render(), render_map(&map.unwrap()));
+        }
+    }
+
+    #[test]
+    pub fn test_simulation_rounds() {
+        let mut simulation = SeatSimulation::new(&example_map(), &SeatingRules::part2());
+        // Line of sight skips the floor: the seat at row 2, column 0 sees row 4 going down.
+        assert_eq!(simulation.neighbours(2, 0).map(|n| n.len()), Some(5));
+        assert_eq!(simulation.neighbours(0, 1), None);
+        assert_eq!(simulation.step(), 71);
+        assert_eq!(simulation.count_occupied(), 71);
+        assert_eq!(simulation.render()[0], "#.##.##.##");
+        assert_eq!(simulation.step(), 64);
+        assert_eq!(simulation.render()[0], "#.LL.LL.L#");
+        assert_eq!(simulation.run_until_stable(DEFAULT_ROUND_LIMIT), Ok(6));
+        assert_eq!(simulation.generation(), 7);
+        assert_eq!(simulation.count_occupied(), 26);
+    }
+
+    #[test]
+    pub fn test_cycle() {
+        // Two seats watching each other, both leaving and sitting down again together.
+        let pair = vec!["LL".to_string()];
+        let rules = SeatingRules {
+            neighbourhood: Neighbourhood::Adjacent,
+            birth_threshold: 0,
+            leave_threshold: 1,
+        };
+        let cycle = SimulationError::Cycle {
+            start: 0,
+            period: 2,
+        };
+        assert_eq!(count_occupied_when_stable(&pair, &rules), Err(cycle));
+        assert_eq!(
+            count_occupied_when_stable_full_scan(&pair, &rules),
+            Err(cycle)
+        );
+        assert_eq!(
+            cycle.to_string(),
+            "seating repeats every 2 rounds from round 0"
+        );
+
+        let rules = SeatingRules {
+            neighbourhood: Neighbourhood::LineOfSight(Some(2)),
+            birth_threshold: 1,
+            leave_threshold: 4,
+        };
+        let mut simulation = SeatSimulation::new(&example_map(), &rules);
+        let result = simulation.run_until_stable(DEFAULT_ROUND_LIMIT);
+        assert_eq!(
+            result,
+            iterate_until_stable(&parse_map(&example_map()), &rules, DEFAULT_ROUND_LIMIT)
+                .map(|_| 0)
+        );
+        assert!(matches!(result, Err(SimulationError::Cycle { .. })));
+    }
+
+    #[test]
+    pub fn test_round_limit() {
+        let mut simulation = SeatSimulation::new(&example_map(), &SeatingRules::part1());
+        assert_eq!(
+            simulation.run_until_stable(3),
+            Err(SimulationError::RoundLimit(3))
+        );
+        assert_eq!(simulation.generation(), 3);
+        // The limit counts from the current round.
+        assert_eq!(simulation.run_until_stable(3), Ok(5));
+        assert_eq!(simulation.count_occupied(), 37);
+        assert_eq!(
+            iterate_until_stable(&parse_map(&example_map()), &SeatingRules::part1(), 5),
+            Err(SimulationError::RoundLimit(5))
+        );
+    }
 }
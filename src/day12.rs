@@ -43,67 +43,387 @@
 
 use lazy_static::lazy_static;
 use regex::{Captures, Regex};
+use std::fmt;
+use std::ops::{Add, Mul, Sub};
 
-#[derive(PartialEq, Debug, Clone)]
-enum Instruction {
-    N(i32),
-    S(i32),
-    E(i32),
-    W(i32),
-    A(i32),
-    F(i32),
+/// A position or a displacement, east and north being positive.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct Vector {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Vector {
+    pub fn new(x: f64, y: f64) -> Vector {
+        Vector { x, y }
+    }
+
+    /// Rotates around the origin. Multiples of 90º only swap and negate the coordinates, so
+    /// integer positions stay exact; any other angle goes through floating point trigonometry.
+    pub fn rotate_clockwise(self, degrees: f64) -> Vector {
+        let degrees = degrees.rem_euclid(360.0);
+        if degrees % 90.0 == 0.0 {
+            // Quarter turns modulo 4, as rem_euclid rounds tiny negative angles up to 360.
+            match (degrees / 90.0) as i32 % 4 {
+                0 => self,
+                1 => Vector::new(self.y, -self.x),
+                2 => Vector::new(-self.x, -self.y),
+                _ => Vector::new(-self.y, self.x),
+            }
+        } else {
+            let (sin, cos) = degrees.to_radians().sin_cos();
+            Vector::new(self.x * cos + self.y * sin, self.y * cos - self.x * sin)
+        }
+    }
+
+    pub fn manhattan_distance(self) -> f64 {
+        self.x.abs() + self.y.abs()
+    }
+
+    /// Euclidean length.
+    pub fn length(self) -> f64 {
+        self.x.hypot(self.y)
+    }
+}
+
+impl Add for Vector {
+    type Output = Vector;
+
+    fn add(self, other: Vector) -> Vector {
+        Vector::new(self.x + other.x, self.y + other.y)
+    }
 }
 
-fn parse_instruction(line: &str) -> Instruction {
+impl Sub for Vector {
+    type Output = Vector;
+
+    fn sub(self, other: Vector) -> Vector {
+        Vector::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Mul<f64> for Vector {
+    type Output = Vector;
+
+    fn mul(self, factor: f64) -> Vector {
+        Vector::new(self.x * factor, self.y * factor)
+    }
+}
+
+/// E.g. `east 17, south 8`.
+impl fmt::Display for Vector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let east_west = if self.x < 0.0 { "west" } else { "east" };
+        let north_south = if self.y < 0.0 { "south" } else { "north" };
+        write!(
+            f,
+            "{} {}, {} {}",
+            east_west,
+            self.x.abs(),
+            north_south,
+            self.y.abs()
+        )
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Instruction {
+    N(f64),
+    S(f64),
+    E(f64),
+    W(f64),
+    /// Rotation in degrees, clockwise.
+    A(f64),
+    F(f64),
+    /// Moves backwards, the opposite of F.
+    B(f64),
+    /// Moves the ship onto the waypoint, which stays where it is.
+    T,
+}
+
+/// Which instructions are accepted.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Syntax {
+    /// The puzzle instructions, with integer values. Rotations can be of any angle.
+    Standard,
+    /// Also decimal values, B and T.
+    Extended,
+}
+
+pub fn parse_instruction(line: &str, syntax: Syntax) -> Instruction {
     lazy_static! {
         static ref REGEX: Regex = Regex::new(r"^([NSEWLRF])(\d+)$").unwrap();
+        static ref EXTENDED_REGEX: Regex =
+            Regex::new(r"^(?:([NSEWLRFB])(\d+(?:\.\d+)?)|(T))$").unwrap();
     }
-    let cap: Captures = REGEX
+    let regex: &Regex = match syntax {
+        Syntax::Standard => &REGEX,
+        Syntax::Extended => &EXTENDED_REGEX,
+    };
+    let cap: Captures = regex
         .captures(line)
         .unwrap_or_else(|| panic!("Unexpected line: {}", line));
+    if cap.get(3).is_some() {
+        return Instruction::T;
+    }
     let action = cap.get(1).unwrap().as_str();
-    let value = cap.get(2).unwrap().as_str().parse::<i32>().unwrap();
+    let value = cap.get(2).unwrap().as_str().parse::<f64>().unwrap();
     match action {
         "N" => Instruction::N(value),
         "S" => Instruction::S(value),
         "E" => Instruction::E(value),
         "W" => Instruction::W(value),
-        "L" => Instruction::A((-value).rem_euclid(360)),
-        "R" => Instruction::A((value).rem_euclid(360)),
+        "L" => Instruction::A(-value),
+        "R" => Instruction::A(value),
         "F" => Instruction::F(value),
+        "B" => Instruction::B(value),
         _ => panic!("Unexpected instruction: {}", line),
     }
 }
 
-fn parse_instructions(lines: &[String]) -> Vec<Instruction> {
-    lines.iter().map(|line| parse_instruction(line)).collect()
+pub fn parse_instructions(lines: &[String], syntax: Syntax) -> Vec<Instruction> {
+    lines
+        .iter()
+        .map(|line| parse_instruction(line, syntax))
+        .collect()
 }
 
-pub fn part1(lines: &[String]) -> i32 {
-    let instructions = parse_instructions(lines);
-
-    // 0º = E; rotation clockwise
-    let mut angle = 0;
-    let mut x = 0;
-    let mut y = 0;
-
-    for instruction in instructions {
-        match instruction {
-            Instruction::N(value) => y += value,
-            Instruction::S(value) => y -= value,
-            Instruction::E(value) => x += value,
-            Instruction::W(value) => x -= value,
-            Instruction::A(value) => angle = (angle + value).rem_euclid(360),
-            Instruction::F(value) => match angle {
-                270 => y += value,
-                90 => y -= value,
-                0 => x += value,
-                180 => x -= value,
-                _ => panic!("Unexpected angle {}", angle),
+/// Why a ship can't follow an instruction.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum NavigationError {
+    /// T teleports onto the waypoint, and ships in heading mode don't have one.
+    NoWaypoint,
+}
+
+impl fmt::Display for NavigationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NavigationError::NoWaypoint => write!(f, "can't teleport without a waypoint"),
+        }
+    }
+}
+
+/// What N, S, E and W move.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Mode {
+    /// The ship itself (part 1). The waypoint is the direction the ship faces, of length 1.
+    Heading,
+    /// The waypoint (part 2).
+    Waypoint,
+}
+
+/// A ship following navigation instructions. In both modes F moves the ship by the waypoint,
+/// relative to the ship, the given number of times, and L and R rotate the waypoint around it.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Ship {
+    pub mode: Mode,
+    pub position: Vector,
+    /// Relative to the ship.
+    pub waypoint: Vector,
+}
+
+impl Ship {
+    /// At the origin facing east.
+    pub fn heading() -> Ship {
+        Ship {
+            mode: Mode::Heading,
+            position: Vector::default(),
+            waypoint: Vector::new(1.0, 0.0),
+        }
+    }
+
+    /// At the origin with the waypoint 10 units east and 1 unit north.
+    pub fn with_waypoint() -> Ship {
+        Ship {
+            mode: Mode::Waypoint,
+            position: Vector::default(),
+            waypoint: Vector::new(10.0, 1.0),
+        }
+    }
+
+    /// Fails without moving anything if the instruction can't be followed.
+    pub fn execute(&mut self, instruction: Instruction) -> Result<(), NavigationError> {
+        let displacement = match instruction {
+            Instruction::N(value) => Vector::new(0.0, value),
+            Instruction::S(value) => Vector::new(0.0, -value),
+            Instruction::E(value) => Vector::new(value, 0.0),
+            Instruction::W(value) => Vector::new(-value, 0.0),
+            Instruction::A(degrees) => {
+                self.waypoint = self.waypoint.rotate_clockwise(degrees);
+                return Ok(());
+            }
+            Instruction::F(value) => {
+                self.position = self.position + self.waypoint * value;
+                return Ok(());
+            }
+            Instruction::B(value) => {
+                self.position = self.position - self.waypoint * value;
+                return Ok(());
+            }
+            Instruction::T => {
+                if self.mode == Mode::Heading {
+                    return Err(NavigationError::NoWaypoint);
+                }
+                self.position = self.position + self.waypoint;
+                self.waypoint = Vector::default();
+                return Ok(());
+            }
+        };
+        match self.mode {
+            Mode::Heading => self.position = self.position + displacement,
+            Mode::Waypoint => self.waypoint = self.waypoint + displacement,
+        }
+        Ok(())
+    }
+
+    /// Stops at the first instruction that can't be followed.
+    pub fn navigate(&mut self, instructions: &[Instruction]) -> Result<(), NavigationError> {
+        for instruction in instructions {
+            self.execute(*instruction)?;
+        }
+        Ok(())
+    }
+
+    /// Manhattan distance from the origin.
+    pub fn distance(&self) -> f64 {
+        self.position.manhattan_distance()
+    }
+
+    /// Same as `navigate`, recording the positions along the way.
+    pub fn record(&mut self, instructions: &[Instruction]) -> Result<Trajectory, NavigationError> {
+        let mut trajectory = Trajectory {
+            ship: vec![self.position],
+            waypoint: Vec::new(),
+        };
+        if self.mode == Mode::Waypoint {
+            trajectory.waypoint.push(self.position + self.waypoint);
+        }
+        for instruction in instructions {
+            self.execute(*instruction)?;
+            trajectory.ship.push(self.position);
+            if self.mode == Mode::Waypoint {
+                trajectory.waypoint.push(self.position + self.waypoint);
+            }
+        }
+        Ok(trajectory)
+    }
+}
+
+/// Smallest rectangle containing some points.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct BoundingBox {
+    pub min: Vector,
+    pub max: Vector,
+}
+
+impl BoundingBox {
+    /// None if there are no points.
+    pub fn of(points: &[Vector]) -> Option<BoundingBox> {
+        let first = *points.first()?;
+        Some(points.iter().fold(
+            BoundingBox {
+                min: first,
+                max: first,
+            },
+            |bounds, point| BoundingBox {
+                min: Vector::new(bounds.min.x.min(point.x), bounds.min.y.min(point.y)),
+                max: Vector::new(bounds.max.x.max(point.x), bounds.max.y.max(point.y)),
             },
+        ))
+    }
+
+    pub fn width(&self) -> f64 {
+        self.max.x - self.min.x
+    }
+
+    pub fn height(&self) -> f64 {
+        self.max.y - self.min.y
+    }
+}
+
+/// Positions of the ship before and after each instruction, and of the waypoint when navigating
+/// with one. Waypoint positions are absolute, not relative to the ship.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Trajectory {
+    pub ship: Vec<Vector>,
+    pub waypoint: Vec<Vector>,
+}
+
+impl Trajectory {
+    /// Bounding box of the ship positions.
+    pub fn bounding_box(&self) -> BoundingBox {
+        BoundingBox::of(&self.ship).unwrap()
+    }
+
+    /// Length of the route of the ship, moving in straight lines.
+    pub fn distance_travelled(&self) -> f64 {
+        self.ship
+            .windows(2)
+            .map(|segment| (segment[1] - segment[0]).length())
+            .sum()
+    }
+
+    /// The first of the ship positions with the highest Manhattan distance from the origin.
+    pub fn furthest_point(&self) -> Vector {
+        self.ship
+            .iter()
+            .copied()
+            .fold(self.ship[0], |furthest, point| {
+                if point.manhattan_distance() > furthest.manhattan_distance() {
+                    point
+                } else {
+                    furthest
+                }
+            })
+    }
+
+    /// SVG image with the route of the ship as a polyline, and the one of the waypoint dashed.
+    /// North is up, the start is marked with a circle.
+    pub fn to_svg(&self) -> String {
+        let points: Vec<Vector> = self.ship.iter().chain(&self.waypoint).copied().collect();
+        let bounds = BoundingBox::of(&points).unwrap();
+        let margin = (bounds.width().max(bounds.height()) * 0.05).max(1.0);
+        // SVG coordinates grow downwards. Adding 0 turns -0 into 0.
+        let svg_point = |point: &Vector| format!("{},{}", point.x, -point.y + 0.0);
+        let polyline = |points: &[Vector], style: &str| {
+            let points: Vec<String> = points.iter().map(svg_point).collect();
+            format!(
+                "  <polyline points=\"{}\" fill=\"none\" {} \
+                vector-effect=\"non-scaling-stroke\"/>",
+                points.join(" "),
+                style
+            )
+        };
+        let mut lines = vec![format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">",
+            bounds.min.x - margin,
+            -bounds.max.y - margin,
+            bounds.width() + 2.0 * margin,
+            bounds.height() + 2.0 * margin
+        )];
+        if !self.waypoint.is_empty() {
+            lines.push(polyline(
+                &self.waypoint,
+                "stroke=\"orange\" stroke-dasharray=\"4\"",
+            ));
         }
+        lines.push(polyline(&self.ship, "stroke=\"steelblue\""));
+        lines.push(format!(
+            "  <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"steelblue\"/>",
+            self.ship[0].x,
+            -self.ship[0].y + 0.0,
+            margin / 2.0
+        ));
+        lines.push("</svg>".to_string());
+        lines.join("\n")
     }
-    x.abs() + y.abs()
+}
+
+pub fn part1(lines: &[String]) -> i32 {
+    let mut ship = Ship::heading();
+    ship.navigate(&parse_instructions(lines, Syntax::Standard))
+        .unwrap_or_else(|error| panic!("Unexpected instructions: {}", error));
+    ship.distance().round() as i32
 }
 
 // --- Part Two ---
@@ -145,49 +465,10 @@ pub fn part1(lines: &[String]) -> i32 {
 // between that location and the ship's starting position?
 
 pub fn part2(lines: &[String]) -> i32 {
-    let instructions = parse_instructions(lines);
-
-    let mut x = 0;
-    let mut y = 0;
-    let mut waypoint_x = 10;
-    let mut waypoint_y = 1;
-
-    for instruction in instructions {
-        match instruction {
-            Instruction::N(value) => waypoint_y += value,
-            Instruction::S(value) => waypoint_y -= value,
-            Instruction::E(value) => waypoint_x += value,
-            Instruction::W(value) => waypoint_x -= value,
-            Instruction::A(angle) => {
-                match angle {
-                    0 => {}
-                    90 => {
-                        // x -> -y, y -> x
-                        let tmp_x = waypoint_x;
-                        waypoint_x = waypoint_y;
-                        waypoint_y = -tmp_x;
-                    }
-                    180 => {
-                        // x -> -x, y -> -y
-                        waypoint_x = -waypoint_x;
-                        waypoint_y = -waypoint_y;
-                    }
-                    270 => {
-                        // x -> y, y -> -x
-                        let tmp_x = waypoint_x;
-                        waypoint_x = -waypoint_y;
-                        waypoint_y = tmp_x;
-                    }
-                    _ => panic!("Unexpected angle {}", angle),
-                }
-            }
-            Instruction::F(value) => {
-                x += value * waypoint_x;
-                y += value * waypoint_y;
-            }
-        }
-    }
-    x.abs() + y.abs()
+    let mut ship = Ship::with_waypoint();
+    ship.navigate(&parse_instructions(lines, Syntax::Standard))
+        .unwrap_or_else(|error| panic!("Unexpected instructions: {}", error));
+    ship.distance().round() as i32
 }
 
 #[cfg(test)]
@@ -207,4 +488,125 @@ mod tests {
         let input: Vec<String> = input_text.lines().map(|line| line.to_string()).collect();
         assert_eq!(part2(&input), 286);
     }
+
+    #[test]
+    pub fn test_rotations() {
+        let vector = Vector::new(10.0, 4.0);
+        assert_eq!(vector.rotate_clockwise(90.0), Vector::new(4.0, -10.0));
+        assert_eq!(vector.rotate_clockwise(-90.0), Vector::new(-4.0, 10.0));
+        assert_eq!(vector.rotate_clockwise(540.0), Vector::new(-10.0, -4.0));
+        let rotated = vector.rotate_clockwise(45.0).rotate_clockwise(45.0);
+        assert!((rotated - Vector::new(4.0, -10.0)).manhattan_distance() < 1e-9);
+        let rotated = Vector::new(2.0, 0.0).rotate_clockwise(-60.0);
+        assert!((rotated - Vector::new(1.0, 3f64.sqrt())).manhattan_distance() < 1e-9);
+        // Rounds up to a full turn.
+        assert_eq!((-1e-20_f64).rem_euclid(360.0), 360.0);
+        assert_eq!(vector.rotate_clockwise(-1e-20), vector);
+    }
+
+    #[test]
+    pub fn test_arbitrary_angles() {
+        let input: Vec<String> = vec!["L45", "F10", "R90", "F10"]
+            .into_iter()
+            .map(|line| line.to_string())
+            .collect();
+        let mut ship = Ship::heading();
+        ship.navigate(&parse_instructions(&input, Syntax::Standard))
+            .unwrap();
+        // Ten units north-east, then ten south-east.
+        let expected = Vector::new(2.0 * 50f64.sqrt(), 0.0);
+        assert!((ship.position - expected).manhattan_distance() < 1e-9);
+    }
+
+    #[test]
+    pub fn test_extended_syntax() {
+        assert_eq!(
+            parse_instruction("L22.5", Syntax::Extended),
+            Instruction::A(-22.5)
+        );
+        assert_eq!(parse_instruction("T", Syntax::Extended), Instruction::T);
+
+        let input_text = "F10\nB2.5\nN3\nT\nF7\nE1\nR90\nF2";
+        let input: Vec<String> = input_text.lines().map(|line| line.to_string()).collect();
+        let mut ship = Ship::with_waypoint();
+        ship.navigate(&parse_instructions(&input, Syntax::Extended))
+            .unwrap();
+        // The teleport leaves the waypoint on the ship, so F7 doesn't move it.
+        assert_eq!(ship.position, Vector::new(85.0, 9.5));
+        assert_eq!(ship.waypoint, Vector::new(0.0, -1.0));
+        assert_eq!(ship.position.to_string(), "east 85, north 9.5");
+    }
+
+    #[test]
+    pub fn test_teleport_without_waypoint() {
+        let mut ship = Ship::heading();
+        let instructions = vec![Instruction::F(2.0), Instruction::T, Instruction::F(3.0)];
+        assert_eq!(
+            ship.navigate(&instructions),
+            Err(NavigationError::NoWaypoint)
+        );
+        assert_eq!(ship.position, Vector::new(2.0, 0.0));
+        assert_eq!(ship.waypoint, Vector::new(1.0, 0.0));
+        assert_eq!(
+            Ship::heading().record(&instructions),
+            Err(NavigationError::NoWaypoint)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Unexpected line: B3")]
+    pub fn test_extended_instruction_in_standard_syntax() {
+        parse_instruction("B3", Syntax::Standard);
+    }
+
+    #[test]
+    pub fn test_trajectory() {
+        let input_text = "F10\nN3\nF7\nR90\nF11";
+        let input: Vec<String> = input_text.lines().map(|line| line.to_string()).collect();
+        let instructions = parse_instructions(&input, Syntax::Standard);
+
+        let trajectory = Ship::heading().record(&instructions).unwrap();
+        assert_eq!(trajectory.ship.len(), 6);
+        assert_eq!(trajectory.ship[5], Vector::new(17.0, -8.0));
+        assert!(trajectory.waypoint.is_empty());
+        assert_eq!(
+            trajectory.bounding_box(),
+            BoundingBox {
+                min: Vector::new(0.0, -8.0),
+                max: Vector::new(17.0, 3.0),
+            }
+        );
+        assert_eq!(trajectory.distance_travelled(), 31.0);
+        assert_eq!(trajectory.furthest_point(), Vector::new(17.0, -8.0));
+
+        let trajectory = Ship::with_waypoint().record(&instructions).unwrap();
+        assert_eq!(trajectory.ship[3], Vector::new(170.0, 38.0));
+        assert_eq!(trajectory.waypoint[0], Vector::new(10.0, 1.0));
+        assert_eq!(trajectory.waypoint[4], Vector::new(174.0, 28.0));
+        assert_eq!(trajectory.furthest_point(), Vector::new(214.0, -72.0));
+        assert_eq!(trajectory.bounding_box().height(), 110.0);
+    }
+
+    #[test]
+    pub fn test_svg() {
+        let instructions = vec![
+            Instruction::F(3.0),
+            Instruction::A(-90.0),
+            Instruction::F(4.0),
+        ];
+        let svg = Ship::heading().record(&instructions).unwrap().to_svg();
+        let lines: Vec<&str> = svg.lines().collect();
+        assert_eq!(
+            lines[0],
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-1 -5 5 6\">"
+        );
+        assert!(lines[1].contains("points=\"0,0 3,0 3,0 3,-4\""));
+        assert_eq!(lines.len(), 4);
+
+        let svg = Ship::with_waypoint()
+            .record(&instructions)
+            .unwrap()
+            .to_svg();
+        assert!(svg.contains("points=\"10,-1 40,-4 29,-13 25,-53\""));
+    }
 }
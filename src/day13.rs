@@ -43,6 +43,8 @@
 //
 // What is the ID of the earliest bus you can take to the airport multiplied by the number of minutes you'll need to wait for that bus?
 
+use crate::number_theory::{chinese_remainder, lcm, Congruence, CongruenceError};
+
 fn parse(lines: &[String]) -> (i64, Vec<(i64, i64)>) {
     let earliest_estimate = lines[0].parse::<i64>().unwrap();
     let bus_lines = lines[1]
@@ -134,61 +136,28 @@ pub fn part2_search(lines: &[String]) -> i64 {
     let (timestamp, _) = bus_lines
         .iter()
         .fold((0, 1), |(timestamp, step), (offset, frequency)| {
-            let mut timestamp = timestamp;
-            while (timestamp + offset) % frequency != 0 {
-                timestamp += step;
-            }
-            // The steps should be the L.C.M., but they are all primes
-            (timestamp, step * frequency)
+            // Adding the step cycles through at most `frequency` remainders.
+            let timestamp = (0..*frequency)
+                .map(|i| timestamp + i * step)
+                .find(|timestamp| (timestamp + offset) % frequency == 0)
+                .unwrap_or_else(|| panic!("Unexpected bus {} at offset {}", frequency, offset));
+            (timestamp, lcm(step, *frequency).unwrap())
         });
     timestamp
 }
 
-pub fn part2_chinese_remainder_theorem(lines: &[String]) -> i64 {
+/// Earliest timestamp where every bus departs at its offset. Bus IDs don't need to be coprime.
+pub fn earliest_timestamp(lines: &[String]) -> Result<i64, CongruenceError> {
     let (_, bus_lines) = parse(lines);
-    let mod_and_residues = bus_lines
+    let congruences: Vec<Congruence> = bus_lines
         .iter()
-        .map(|(offset, bus_line)| (*bus_line, *bus_line - (*offset % *bus_line)))
-        .collect::<Vec<(i64, i64)>>();
-    chinese_remainder(
-        &mod_and_residues.iter().map(|x| x.1).collect::<Vec<i64>>(),
-        &mod_and_residues.iter().map(|x| x.0).collect::<Vec<i64>>(),
-    )
-    .unwrap()
-}
-
-// Chinese remainder theorem, implementation from rosettacode.
-// https://rosettacode.org/wiki/Chinese_remainder_theorem#Rust
-#[allow(clippy::many_single_char_names)]
-fn egcd(a: i64, b: i64) -> (i64, i64, i64) {
-    if a == 0 {
-        (b, 0, 1)
-    } else {
-        let (g, x, y) = egcd(b % a, a);
-        (g, y - (b / a) * x, x)
-    }
+        .map(|(offset, bus_line)| Congruence::new(-offset, *bus_line))
+        .collect();
+    Ok(chinese_remainder(&congruences)?.residue)
 }
 
-fn mod_inv(x: i64, n: i64) -> Option<i64> {
-    let (g, x, _) = egcd(x, n);
-    if g == 1 {
-        Some((x % n + n) % n)
-    } else {
-        None
-    }
-}
-
-fn chinese_remainder(residues: &[i64], modulii: &[i64]) -> Option<i64> {
-    let prod = modulii.iter().product::<i64>();
-
-    let mut sum = 0;
-
-    for (&residue, &modulus) in residues.iter().zip(modulii) {
-        let p = prod / modulus;
-        sum += residue * mod_inv(p, modulus)? * p
-    }
-
-    Some(sum % prod)
+pub fn part2_chinese_remainder_theorem(lines: &[String]) -> i64 {
+    earliest_timestamp(lines).unwrap_or_else(|error| panic!("Unexpected schedule: {}", error))
 }
 
 #[cfg(test)]
@@ -230,4 +199,31 @@ mod tests {
             assert_eq!(part2_chinese_remainder_theorem(&input), 1202161486);
         }
     }
+
+    #[test]
+    pub fn test_not_coprime_bus_ids() {
+        let input_text = "939\n4,x,6";
+        let input: Vec<String> = input_text.lines().map(|line| line.to_string()).collect();
+        assert_eq!(earliest_timestamp(&input), Ok(4));
+        assert_eq!(part2_search(&input), 4);
+
+        // Bus 4 leaves at even timestamps, and so would bus 6 at an odd one.
+        let input_text = "939\n4,6";
+        let input: Vec<String> = input_text.lines().map(|line| line.to_string()).collect();
+        assert_eq!(
+            earliest_timestamp(&input),
+            Err(CongruenceError::Inconsistent(
+                Congruence::new(0, 4),
+                Congruence::new(5, 6)
+            ))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Unexpected bus 6 at offset 1")]
+    pub fn test_search_inconsistent_bus_ids() {
+        let input_text = "939\n4,6";
+        let input: Vec<String> = input_text.lines().map(|line| line.to_string()).collect();
+        part2_search(&input);
+    }
 }
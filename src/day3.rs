@@ -131,6 +131,111 @@ pub fn calculate_slopes_cost_multiplied(lines: &[String]) -> i64 {
         .unwrap()
 }
 
+// Bit-packed version of the map, for generated maps much larger than the puzzle input.
+// Each row is stored as a run of u64 words where bit `i % 64` of word `i / 64` is the cell in
+// column `i`. Rows are padded to whole words so row level queries can use `count_ones`.
+
+const WORD_BITS: usize = 64;
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct TreeMap {
+    width: usize,
+    height: usize,
+    words_per_row: usize,
+    words: Vec<u64>,
+}
+
+impl TreeMap {
+    pub fn parse(lines: &[String]) -> TreeMap {
+        let width = lines.first().map_or(0, |line| line.len());
+        let height = lines.len();
+        let words_per_row = width.div_ceil(WORD_BITS);
+        let mut words = vec![0_u64; words_per_row * height];
+        for (row, line) in lines.iter().enumerate() {
+            if line.len() != width {
+                panic!("Unexpected row width {} at row {}", line.len(), row);
+            }
+            for (column, is_tree) in parse_map_line(line).into_iter().enumerate() {
+                if is_tree {
+                    words[row * words_per_row + column / WORD_BITS] |= 1 << (column % WORD_BITS);
+                }
+            }
+        }
+        TreeMap {
+            width,
+            height,
+            words_per_row,
+            words,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn row_words(&self, row: usize) -> &[u64] {
+        &self.words[row * self.words_per_row..(row + 1) * self.words_per_row]
+    }
+
+    /// Whether there is a tree at the given position. The map repeats to the right, so the column
+    /// wraps around the width. A map without columns has no trees.
+    pub fn is_tree(&self, row: usize, column: usize) -> bool {
+        if self.width == 0 {
+            return false;
+        }
+        let column = column % self.width;
+        self.row_words(row)[column / WORD_BITS] & (1 << (column % WORD_BITS)) != 0
+    }
+
+    /// Number of trees in a single row of the map.
+    pub fn trees_in_row(&self, row: usize) -> usize {
+        self.row_words(row)
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Number of trees in the given column, wrapping the column around the width.
+    pub fn trees_in_column(&self, column: usize) -> usize {
+        (0..self.height)
+            .filter(|row| self.is_tree(*row, column))
+            .count()
+    }
+
+    /// Number of trees on the whole map.
+    pub fn count_trees(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Number of trees hit going from the top-left corner with the given slope.
+    pub fn count_trees_on_slope(&self, x_slope: usize, y_slope: usize) -> usize {
+        (0..self.height)
+            .step_by(y_slope)
+            .enumerate()
+            .filter(|(step, row)| self.is_tree(*row, step * x_slope))
+            .count()
+    }
+}
+
+pub fn calculate_cut_trees_for_cheap_toboggan_packed(lines: &[String]) -> usize {
+    TreeMap::parse(lines).count_trees_on_slope(3, 1)
+}
+
+pub fn calculate_slopes_cost_multiplied_packed(lines: &[String]) -> usize {
+    let tree_map = TreeMap::parse(lines);
+    [(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)]
+        .iter()
+        .map(|(x_slope, y_slope)| tree_map.count_trees_on_slope(*x_slope, *y_slope))
+        .product()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -149,4 +254,60 @@ mod tests {
             vec![false, false, true, true, false, false, false, false, false, false, false]
         );
     }
+
+    fn example_map() -> Vec<String> {
+        let input_text = "..##.......\n#...#...#..\n.#....#..#.\n..#.#...#.#\n.#...##..#.\n\
+            ..#.##.....\n.#.#.#....#\n.#........#\n#.##...#...\n#...##....#\n.#..#...#.#";
+        input_text.lines().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    pub fn test_tree_map_queries() {
+        let tree_map = TreeMap::parse(&example_map());
+        assert_eq!(tree_map.width(), 11);
+        assert_eq!(tree_map.height(), 11);
+        assert!(tree_map.is_tree(0, 2));
+        assert!(tree_map.is_tree(0, 13));
+        assert!(!tree_map.is_tree(0, 11));
+        assert_eq!(tree_map.trees_in_row(0), 2);
+        assert_eq!(tree_map.trees_in_row(10), 4);
+        assert_eq!(tree_map.trees_in_column(0), 3);
+        assert_eq!(tree_map.trees_in_column(11), 3);
+        assert_eq!(tree_map.count_trees(), 37);
+    }
+
+    #[test]
+    pub fn test_tree_map_slopes() {
+        let tree_map = TreeMap::parse(&example_map());
+        let slopes = [(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)];
+        let trees: Vec<usize> = slopes
+            .iter()
+            .map(|(x_slope, y_slope)| tree_map.count_trees_on_slope(*x_slope, *y_slope))
+            .collect();
+        assert_eq!(trees, vec![2, 7, 3, 4, 2]);
+        assert_eq!(calculate_slopes_cost_multiplied_packed(&example_map()), 336);
+        assert_eq!(calculate_slopes_cost_multiplied(&example_map()), 336);
+    }
+
+    #[test]
+    pub fn test_tree_map_wide_rows() {
+        let row = format!("{}#{}", ".".repeat(63), "#.".repeat(40));
+        let tree_map = TreeMap::parse(&[row.clone(), row]);
+        assert_eq!(tree_map.width(), 144);
+        assert_eq!(tree_map.trees_in_row(1), 41);
+        assert!(tree_map.is_tree(1, 63));
+        assert!(tree_map.is_tree(1, 64));
+        assert!(!tree_map.is_tree(1, 65));
+        assert!(tree_map.is_tree(1, 144 + 63));
+    }
+
+    #[test]
+    pub fn test_tree_map_without_columns() {
+        let tree_map = TreeMap::parse(&["".to_string(), "".to_string()]);
+        assert_eq!(tree_map.width(), 0);
+        assert!(!tree_map.is_tree(1, 5));
+        assert_eq!(tree_map.trees_in_column(3), 0);
+        assert_eq!(tree_map.count_trees_on_slope(3, 1), 0);
+        assert_eq!(TreeMap::parse(&[]).count_trees_on_slope(3, 1), 0);
+    }
 }
@@ -54,62 +54,87 @@
 // Count the number of valid passports - those that have all required fields. Treat cid as optional.
 // In your batch file, how many passports are valid?
 
-pub fn count_passwords_with_all_fields(lines: &[String]) -> i32 {
-    let mut byr = false;
-    let mut iyr = false;
-    let mut eyr = false;
-    let mut hgt = false;
-    let mut hcl = false;
-    let mut ecl = false;
-    let mut pid = false;
-
-    let mut count = 0;
-    let mut iter = lines.iter();
-    loop {
-        let line = iter.next();
-        match line {
-            Some(x) if !x.is_empty() => {
-                if x.contains("byr") {
-                    byr = true
-                }
-                if x.contains("iyr") {
-                    iyr = true
-                }
-                if x.contains("eyr") {
-                    eyr = true
-                }
-                if x.contains("hgt") {
-                    hgt = true
-                }
-                if x.contains("hcl") {
-                    hcl = true
-                }
-                if x.contains("ecl") {
-                    ecl = true
-                }
-                if x.contains("pid") {
-                    pid = true
-                }
-            }
-            _ => {
-                if byr && iyr && eyr && hgt && hcl && ecl && pid {
-                    count += 1;
-                }
-                if line.is_none() {
-                    break;
-                } else {
-                    byr = false;
-                    iyr = false;
-                    eyr = false;
-                    hgt = false;
-                    hcl = false;
-                    ecl = false;
-                    pid = false;
-                }
-            }
-        }
+/// A passport as a list of `key:value` fields, in the order they appear in the batch file.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Passport {
+    fields: Vec<(String, String)>,
+}
+
+impl Passport {
+    /// Parses a single passport, whose fields may be spread over several lines.
+    pub fn parse(lines: &[String]) -> Passport {
+        let fields = lines
+            .iter()
+            .flat_map(|line| line.split_whitespace())
+            .map(|field| match field.split_once(':') {
+                Some((key, value)) => (key.to_string(), value.to_string()),
+                None => panic!("Unexpected field: {}", field),
+            })
+            .collect();
+        Passport { fields }
+    }
+
+    pub fn fields(&self) -> &[(String, String)] {
+        &self.fields
+    }
+
+    /// Value of the first field with the given key.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field_key, _)| field_key == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Same passport with the fields sorted by key. Repeated keys keep their relative order.
+    pub fn sorted(&self) -> Passport {
+        let mut fields = self.fields.clone();
+        fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Passport { fields }
+    }
+
+    /// All the `key:value` fields on a single line, in their current order.
+    pub fn to_line(&self) -> String {
+        self.fields
+            .iter()
+            .map(|(key, value)| format!("{}:{}", key, value))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    /// A JSON object with the fields in their current order.
+    pub fn to_json(&self) -> String {
+        let members = self
+            .fields
+            .iter()
+            .map(|(key, value)| format!("{}: {}", json_string(key), json_string(value)))
+            .collect::<Vec<String>>();
+        format!("{{{}}}", members.join(", "))
     }
-    count
+}
+
+/// Parses a batch file where passports are separated by blank lines.
+pub fn parse_passports(lines: &[String]) -> Vec<Passport> {
+    lines
+        .split(|line| line.is_empty())
+        .filter(|group| !group.is_empty())
+        .map(Passport::parse)
+        .collect()
+}
+
+pub fn count_passports_with_required_fields(lines: &[String], schema: &Schema) -> usize {
+    parse_passports(lines)
+        .iter()
+        .filter(|passport| schema.has_required_fields(passport))
+        .count()
+}
+
+pub fn count_passwords_with_all_fields(lines: &[String]) -> i32 {
+    count_passports_with_required_fields(lines, puzzle_schema()) as i32
 }
 
 // --- Part Two ---
@@ -180,201 +205,696 @@ pub fn count_passwords_with_all_fields(lines: &[String]) -> i32 {
 // iyr:2010 hgt:158cm hcl:#b6652a ecl:blu byr:1944 eyr:2021 pid:093154719
 // Count the number of valid passports - those that have all required fields and valid values.
 // Continue to treat cid as optional. In your batch file, how many passports are valid?
+use crate::input_utils;
+use crate::output_utils::json_string;
 use lazy_static::lazy_static;
 use regex::Regex;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
 
-/// byr (Birth Year) - four digits; at least 1920 and at most 2002.
-fn has_valid_byr(line: &str) -> bool {
-    lazy_static! {
-        static ref BYR_REGEX: Regex = Regex::new(r"byr:(19[2-9]\d|200[012])\b").unwrap();
-    }
-    BYR_REGEX.is_match(line)
+/// Rule the value of a passport field has to follow.
+#[derive(Debug, Clone)]
+pub enum FieldRule {
+    /// Any value.
+    Any,
+    /// Four digits; at least min and at most max.
+    Year(u32, u32),
+    /// A number followed by one of the units; each unit has its own inclusive range.
+    Measure(Vec<(String, u32, u32)>),
+    /// Exactly one of the values.
+    OneOf(Vec<String>),
+    /// The whole value matches the regex.
+    Pattern(Regex),
 }
 
-/// iyr (Issue Year) - four digits; at least 2010 and at most 2020.
-fn has_valid_iyr(line: &str) -> bool {
-    lazy_static! {
-        static ref IYR_REGEX: Regex = Regex::new(r"iyr:(201\d|2020)\b").unwrap();
+impl FieldRule {
+    /// Parses a rule from its name and arguments, as written in a schema file.
+    fn parse(rule: &str, arguments: &[&str]) -> FieldRule {
+        lazy_static! {
+            static ref MEASURE_REGEX: Regex = Regex::new(r"^(\d+)-(\d+)(\D+)$").unwrap();
+        }
+        match (rule, arguments) {
+            ("any", []) => FieldRule::Any,
+            ("year", [min, max]) => FieldRule::Year(
+                min.parse()
+                    .unwrap_or_else(|_| panic!("Unexpected year: {}", min)),
+                max.parse()
+                    .unwrap_or_else(|_| panic!("Unexpected year: {}", max)),
+            ),
+            ("measure", ranges) if !ranges.is_empty() => FieldRule::Measure(
+                ranges
+                    .iter()
+                    .map(|range| {
+                        let cap = MEASURE_REGEX
+                            .captures(range)
+                            .unwrap_or_else(|| panic!("Unexpected measure: {}", range));
+                        (
+                            cap[3].to_string(),
+                            cap[1].parse().unwrap(),
+                            cap[2].parse().unwrap(),
+                        )
+                    })
+                    .collect(),
+            ),
+            ("one_of", values) if !values.is_empty() => {
+                FieldRule::OneOf(values.iter().map(|value| value.to_string()).collect())
+            }
+            ("pattern", parts) if !parts.is_empty() => {
+                let pattern = format!("^(?:{})$", parts.join(" "));
+                FieldRule::Pattern(
+                    Regex::new(&pattern)
+                        .unwrap_or_else(|_| panic!("Unexpected pattern: {}", pattern)),
+                )
+            }
+            _ => panic!("Unexpected rule: {} {}", rule, arguments.join(" ")),
+        }
     }
-    IYR_REGEX.is_match(line)
-}
 
-/// eyr (Expiration Year) - four digits; at least 2020 and at most 2030.
-fn has_valid_eyr(line: &str) -> bool {
-    lazy_static! {
-        static ref EYR_REGEX: Regex = Regex::new(r"eyr:(202\d|2030)\b").unwrap();
+    pub fn check(&self, value: &str) -> Result<(), ValueError> {
+        match self {
+            FieldRule::Any => Ok(()),
+            FieldRule::Year(min, max) => {
+                if value.len() != 4 || !value.chars().all(|char| char.is_ascii_digit()) {
+                    Err(ValueError::BadFormat)
+                } else if !(*min..=*max).contains(&value.parse::<u32>().unwrap()) {
+                    Err(ValueError::OutOfRange)
+                } else {
+                    Ok(())
+                }
+            }
+            FieldRule::Measure(units) => {
+                let number_end = value
+                    .find(|char: char| !char.is_ascii_digit())
+                    .unwrap_or(value.len());
+                let (number, unit) = value.split_at(number_end);
+                let number = number.parse::<u32>().map_err(|_| ValueError::BadFormat)?;
+                match units.iter().find(|(name, _, _)| name == unit) {
+                    Some((_, min, max)) if (*min..=*max).contains(&number) => Ok(()),
+                    Some(_) => Err(ValueError::OutOfRange),
+                    None => Err(ValueError::BadUnit),
+                }
+            }
+            FieldRule::OneOf(values) => {
+                if values.iter().any(|option| option == value) {
+                    Ok(())
+                } else {
+                    Err(ValueError::NotAllowed)
+                }
+            }
+            FieldRule::Pattern(regex) => {
+                if regex.is_match(value) {
+                    Ok(())
+                } else {
+                    Err(ValueError::BadFormat)
+                }
+            }
+        }
     }
-    EYR_REGEX.is_match(line)
-}
 
-/// hgt (Height) - a number followed by either cm or in:
-/// If cm, the number must be at least 150 and at most 193.
-/// If in, the number must be at least 59 and at most 76.
-fn has_valid_hgt(line: &str) -> bool {
-    lazy_static! {
-        static ref HGT_REGEX: Regex =
-            Regex::new(r"hgt:((?:1[5-8]\d|19[0-3])cm|(?:59|6\d|7[0-6])in)\b").unwrap();
+    pub fn accepts(&self, value: &str) -> bool {
+        self.check(value).is_ok()
     }
-    HGT_REGEX.is_match(line)
 }
 
-/// hcl (Hair Color) - a # followed by exactly six characters 0-9 or a-f.
-fn has_valid_hcl(line: &str) -> bool {
-    lazy_static! {
-        static ref HCL_REGEX: Regex = Regex::new(r"hcl:#[a-f0-9]{6}\b").unwrap();
-    }
-    HCL_REGEX.is_match(line)
+/// Why a field value was rejected by its rule.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+pub enum ValueError {
+    /// Not shaped as the rule expects, e.g. a year that isn't four digits.
+    BadFormat,
+    /// A year or measure outside its range.
+    OutOfRange,
+    /// A measure with a unit the rule doesn't list.
+    BadUnit,
+    /// A value that isn't one of the allowed ones.
+    NotAllowed,
 }
 
-/// ecl (Eye Color) - exactly one of: amb blu brn gry grn hzl oth.
-fn has_valid_ecl(line: &str) -> bool {
-    lazy_static! {
-        static ref ECL_REGEX: Regex = Regex::new(r"ecl:(?:amb|blu|brn|gry|grn|hzl|oth)\b").unwrap();
-    }
-    ECL_REGEX.is_match(line)
+/// Why a passport was rejected. Every variant carries the key of the offending field.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone)]
+pub enum Failure {
+    MissingField(String),
+    UnknownField(String),
+    DuplicateField(String),
+    InvalidValue(String, ValueError),
 }
 
-/// pid (Passport ID) - a nine-digit number, including leading zeroes.
-fn has_valid_pid(line: &str) -> bool {
-    lazy_static! {
-        static ref PID_REGEX: Regex = Regex::new(r"pid:\d{9}\b").unwrap();
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Failure::MissingField(key) => write!(f, "missing {}", key),
+            Failure::UnknownField(key) => write!(f, "unknown field {}", key),
+            Failure::DuplicateField(key) => write!(f, "duplicate {}", key),
+            Failure::InvalidValue(key, ValueError::BadFormat) => write!(f, "bad format in {}", key),
+            Failure::InvalidValue(key, ValueError::OutOfRange) => write!(f, "{} out of range", key),
+            Failure::InvalidValue(key, ValueError::BadUnit) => write!(f, "bad unit in {}", key),
+            Failure::InvalidValue(key, ValueError::NotAllowed) => {
+                write!(f, "{} not an allowed value", key)
+            }
+        }
     }
-    PID_REGEX.is_match(line)
 }
 
-pub fn count_valid_passwords(lines: &[String]) -> i32 {
-    let mut byr = false;
-    let mut iyr = false;
-    let mut eyr = false;
-    let mut hgt = false;
-    let mut hcl = false;
-    let mut ecl = false;
-    let mut pid = false;
-
-    let mut count = 0;
-    let mut iter = lines.iter();
-    loop {
-        let maybe_line = iter.next();
-        match maybe_line {
-            Some(line) if !line.is_empty() => {
-                if has_valid_byr(line) {
-                    byr = true
-                }
-                if has_valid_iyr(line) {
-                    iyr = true
-                }
-                if has_valid_eyr(line) {
-                    eyr = true
-                }
-                if has_valid_hgt(line) {
-                    hgt = true
-                }
-                if has_valid_hcl(line) {
-                    hcl = true
+#[derive(Debug, Clone)]
+pub struct FieldSpec {
+    pub key: String,
+    pub required: bool,
+    pub rule: FieldRule,
+}
+
+/// Fields passports are validated against. Loaded from a schema file (see `inputs/day4_schema`)
+/// so the rules can change without touching the code.
+#[derive(Debug, Clone)]
+pub struct Schema {
+    fields: Vec<FieldSpec>,
+}
+
+impl Schema {
+    /// Parses one `<key> <required|optional> <rule> [arguments...]` field per line. Blank lines and
+    /// lines starting with `#` are ignored.
+    pub fn parse(lines: &[String]) -> Schema {
+        let fields = lines
+            .iter()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let tokens: Vec<&str> = line.split_whitespace().collect();
+                if tokens.len() < 3 {
+                    panic!("Unexpected schema line: {}", line);
                 }
-                if has_valid_ecl(line) {
-                    ecl = true
+                let required = match tokens[1] {
+                    "required" => true,
+                    "optional" => false,
+                    _ => panic!("Unexpected schema line: {}", line),
+                };
+                FieldSpec {
+                    key: tokens[0].to_string(),
+                    required,
+                    rule: FieldRule::parse(tokens[2], &tokens[3..]),
                 }
-                if has_valid_pid(line) {
-                    pid = true
+            })
+            .collect();
+        Schema { fields }
+    }
+
+    pub fn from_file(file_name: &str) -> Schema {
+        Schema::parse(&input_utils::read_all(file_name))
+    }
+
+    pub fn fields(&self) -> &[FieldSpec] {
+        &self.fields
+    }
+
+    pub fn field(&self, key: &str) -> Option<&FieldSpec> {
+        self.fields.iter().find(|field| field.key == key)
+    }
+
+    pub fn has_required_fields(&self, passport: &Passport) -> bool {
+        self.fields
+            .iter()
+            .filter(|field| field.required)
+            .all(|field| passport.contains_key(&field.key))
+    }
+
+    /// Every reason the passport is rejected, empty for a valid passport. Missing fields come
+    /// first, in schema order, followed by problems with the passport fields in passport order.
+    pub fn validate(&self, passport: &Passport) -> Vec<Failure> {
+        let mut failures: Vec<Failure> = self
+            .fields
+            .iter()
+            .filter(|field| field.required && !passport.contains_key(&field.key))
+            .map(|field| Failure::MissingField(field.key.clone()))
+            .collect();
+
+        let mut seen_keys = HashSet::new();
+        for (key, value) in passport.fields() {
+            if !seen_keys.insert(key) {
+                let duplicate = Failure::DuplicateField(key.clone());
+                if !failures.contains(&duplicate) {
+                    failures.push(duplicate);
                 }
+                continue;
             }
-            _ => {
-                if byr && iyr && eyr && hgt && hcl && ecl && pid {
-                    count += 1;
-                }
-                if maybe_line.is_none() {
-                    break;
-                } else {
-                    byr = false;
-                    iyr = false;
-                    eyr = false;
-                    hgt = false;
-                    hcl = false;
-                    ecl = false;
-                    pid = false;
+            match self.field(key) {
+                None => failures.push(Failure::UnknownField(key.clone())),
+                Some(field) => {
+                    if let Err(error) = field.rule.check(value) {
+                        failures.push(Failure::InvalidValue(key.clone(), error));
+                    }
                 }
             }
         }
+        failures
+    }
+
+    /// All required fields are present, once, with valid values, and there are no fields the
+    /// schema doesn't know about.
+    pub fn is_valid(&self, passport: &Passport) -> bool {
+        self.validate(passport).is_empty()
+    }
+}
+
+/// The puzzle rules, read from `inputs/day4_schema` the first time they are needed.
+pub fn puzzle_schema() -> &'static Schema {
+    lazy_static! {
+        static ref PUZZLE_SCHEMA: Schema = Schema::from_file("inputs/day4_schema");
+    }
+    &PUZZLE_SCHEMA
+}
+
+pub fn count_valid_passports(lines: &[String], schema: &Schema) -> usize {
+    parse_passports(lines)
+        .iter()
+        .filter(|passport| schema.is_valid(passport))
+        .count()
+}
+
+pub fn count_valid_passwords(lines: &[String]) -> i32 {
+    count_valid_passports(lines, puzzle_schema()) as i32
+}
+
+/// Groups the rejected passports by failure reason. Each reason maps to the indices of the
+/// passports failing for it, in batch order. A passport failing for several reasons is listed
+/// under each one of them.
+pub fn group_rejected_passports(
+    passports: &[Passport],
+    schema: &Schema,
+) -> BTreeMap<Failure, Vec<usize>> {
+    let mut groups: BTreeMap<Failure, Vec<usize>> = BTreeMap::new();
+    for (index, passport) in passports.iter().enumerate() {
+        for failure in schema.validate(passport) {
+            let indices = groups.entry(failure).or_default();
+            if indices.last() != Some(&index) {
+                indices.push(index);
+            }
+        }
+    }
+    groups
+}
+
+/// Summary of why passports were rejected: one line per failure reason with the number of
+/// passports failing for it, most common reasons first.
+pub fn rejection_summary(lines: &[String], schema: &Schema) -> Vec<String> {
+    let passports = parse_passports(lines);
+    let rejected = passports
+        .iter()
+        .filter(|passport| !schema.is_valid(passport))
+        .count();
+    let mut groups: Vec<(Failure, Vec<usize>)> = group_rejected_passports(&passports, schema)
+        .into_iter()
+        .collect();
+    groups.sort_by_key(|(_, indices)| Reverse(indices.len()));
+
+    let mut summary = vec![format!(
+        "{} of {} passports rejected",
+        rejected,
+        passports.len()
+    )];
+    summary.extend(
+        groups
+            .iter()
+            .map(|(failure, indices)| format!("{:>5} {}", indices.len(), failure)),
+    );
+    summary
+}
+
+/// Output formats when re-serialising a batch of passports.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum BatchFormat {
+    /// Fields sorted by key, one passport per line and a blank line between passports. This is
+    /// still a batch file, so it can be parsed again.
+    Canonical,
+    /// A JSON array with one object per passport, fields sorted by key.
+    Json,
+}
+
+/// Which passports to keep when re-serialising a batch.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Selection {
+    All,
+    Valid,
+    Invalid,
+}
+
+/// Parses a batch file and emits it again in the given format, keeping only the selected
+/// passports. Validity is checked against the schema.
+pub fn normalise_passports(
+    lines: &[String],
+    schema: &Schema,
+    format: BatchFormat,
+    selection: Selection,
+) -> Vec<String> {
+    let passports: Vec<Passport> = parse_passports(lines)
+        .iter()
+        .filter(|passport| match selection {
+            Selection::All => true,
+            Selection::Valid => schema.is_valid(passport),
+            Selection::Invalid => !schema.is_valid(passport),
+        })
+        .map(|passport| passport.sorted())
+        .collect();
+
+    match format {
+        BatchFormat::Canonical => passports
+            .iter()
+            .enumerate()
+            .flat_map(|(index, passport)| {
+                let separator = if index == 0 {
+                    None
+                } else {
+                    Some(String::new())
+                };
+                separator.into_iter().chain(Some(passport.to_line()))
+            })
+            .collect(),
+        BatchFormat::Json => {
+            let last = passports.len().saturating_sub(1);
+            let objects = passports.iter().enumerate().map(|(index, passport)| {
+                let comma = if index == last { "" } else { "," };
+                format!("  {}{}", passport.to_json(), comma)
+            });
+            Some("[".to_string())
+                .into_iter()
+                .chain(objects)
+                .chain(Some("]".to_string()))
+                .collect()
+        }
     }
-    count
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn to_lines(input_text: &str) -> Vec<String> {
+        input_text.lines().map(|line| line.to_string()).collect()
+    }
+
+    /// Checks a single `key:value` field against the puzzle schema.
+    fn has_valid_field(field: &str) -> bool {
+        let passport = Passport::parse(&[field.to_string()]);
+        let (key, value) = &passport.fields()[0];
+        puzzle_schema()
+            .field(key)
+            .is_some_and(|field| field.rule.accepts(value))
+    }
+
     #[test]
-    pub fn test_byr_regex() {
+    pub fn test_byr_rule() {
         // byr (Birth Year) - four digits; at least 1920 and at most 2002.
-        assert_eq!(has_valid_byr(&"byr:1919"), false);
-        assert_eq!(has_valid_byr(&"asdr:1920"), false);
-        assert_eq!(has_valid_byr(&"byr:1920"), true);
-        assert_eq!(has_valid_byr(&"byr:1987"), true);
-        assert_eq!(has_valid_byr(&"byr:2002"), true);
-        assert_eq!(has_valid_byr(&"byr:2003"), false);
-        assert_eq!(has_valid_byr(&"byr:191"), false);
+        assert_eq!(has_valid_field(&"byr:1919"), false);
+        assert_eq!(has_valid_field(&"asdr:1920"), false);
+        assert_eq!(has_valid_field(&"byr:1920"), true);
+        assert_eq!(has_valid_field(&"byr:1987"), true);
+        assert_eq!(has_valid_field(&"byr:2002"), true);
+        assert_eq!(has_valid_field(&"byr:2003"), false);
+        assert_eq!(has_valid_field(&"byr:191"), false);
     }
 
     #[test]
-    pub fn test_iyr_regex() {
+    pub fn test_iyr_rule() {
         // iyr (Issue Year) - four digits; at least 2010 and at most 2020.
-        assert_eq!(has_valid_iyr(&"iyr:1987"), false);
-        assert_eq!(has_valid_iyr(&"asdr:2010"), false);
-        assert_eq!(has_valid_iyr(&"iyr:2010"), true);
-        assert_eq!(has_valid_iyr(&"iyr:2015"), true);
-        assert_eq!(has_valid_iyr(&"iyr:2020"), true);
-        assert_eq!(has_valid_iyr(&"iyr:2021"), false);
-        assert_eq!(has_valid_iyr(&"iyr:191"), false);
+        assert_eq!(has_valid_field(&"iyr:1987"), false);
+        assert_eq!(has_valid_field(&"asdr:2010"), false);
+        assert_eq!(has_valid_field(&"iyr:2010"), true);
+        assert_eq!(has_valid_field(&"iyr:2015"), true);
+        assert_eq!(has_valid_field(&"iyr:2020"), true);
+        assert_eq!(has_valid_field(&"iyr:2021"), false);
+        assert_eq!(has_valid_field(&"iyr:191"), false);
     }
 
     #[test]
-    pub fn test_hgt_regex() {
+    pub fn test_hgt_rule() {
         // hgt (Height) - a number followed by either cm or in:
         // If cm, the number must be at least 150 and at most 193.
-        assert_eq!(has_valid_hgt(&"asdr:150cm"), false);
-        assert_eq!(has_valid_hgt(&"hgt:150ccm"), false);
-        assert_eq!(has_valid_hgt(&"hgt:149cm"), false);
-        assert_eq!(has_valid_hgt(&"hgt:150cm"), true);
-        assert_eq!(has_valid_hgt(&"hgt:177cm"), true);
-        assert_eq!(has_valid_hgt(&"hgt:193cm"), true);
-        assert_eq!(has_valid_hgt(&"hgt:194cm"), false);
+        assert_eq!(has_valid_field(&"asdr:150cm"), false);
+        assert_eq!(has_valid_field(&"hgt:150ccm"), false);
+        assert_eq!(has_valid_field(&"hgt:149cm"), false);
+        assert_eq!(has_valid_field(&"hgt:150cm"), true);
+        assert_eq!(has_valid_field(&"hgt:177cm"), true);
+        assert_eq!(has_valid_field(&"hgt:193cm"), true);
+        assert_eq!(has_valid_field(&"hgt:194cm"), false);
         // If in, the number must be at least 59 and at most 76.
-        assert_eq!(has_valid_hgt(&"asdr:150in"), false);
-        assert_eq!(has_valid_hgt(&"hgt:150cin"), false);
-        assert_eq!(has_valid_hgt(&"hgt:58in"), false);
-        assert_eq!(has_valid_hgt(&"hgt:59in"), true);
-        assert_eq!(has_valid_hgt(&"hgt:63in"), true);
-        assert_eq!(has_valid_hgt(&"hgt:76in"), true);
-        assert_eq!(has_valid_hgt(&"hgt:77in"), false);
+        assert_eq!(has_valid_field(&"asdr:150in"), false);
+        assert_eq!(has_valid_field(&"hgt:150cin"), false);
+        assert_eq!(has_valid_field(&"hgt:58in"), false);
+        assert_eq!(has_valid_field(&"hgt:59in"), true);
+        assert_eq!(has_valid_field(&"hgt:63in"), true);
+        assert_eq!(has_valid_field(&"hgt:76in"), true);
+        assert_eq!(has_valid_field(&"hgt:77in"), false);
     }
 
     #[test]
-    pub fn test_ecl_regex() {
+    pub fn test_ecl_rule() {
         // ecl (Eye Color) - exactly one of: amb blu brn gry grn hzl oth.
-        assert_eq!(has_valid_ecl(&"ecl:ads1"), false);
-        assert_eq!(has_valid_ecl(&"asdr:amb"), false);
-        assert_eq!(has_valid_ecl(&"ecl:amb"), true);
-        assert_eq!(has_valid_ecl(&"ecl:blu"), true);
-        assert_eq!(has_valid_ecl(&"ecl:brn"), true);
-        assert_eq!(has_valid_ecl(&"ecl:gry"), true);
-        assert_eq!(has_valid_ecl(&"ecl:grn"), true);
-        assert_eq!(has_valid_ecl(&"ecl:hzl"), true);
-        assert_eq!(has_valid_ecl(&"ecl:oth"), true);
+        assert_eq!(has_valid_field(&"ecl:ads1"), false);
+        assert_eq!(has_valid_field(&"asdr:amb"), false);
+        assert_eq!(has_valid_field(&"ecl:amb"), true);
+        assert_eq!(has_valid_field(&"ecl:blu"), true);
+        assert_eq!(has_valid_field(&"ecl:brn"), true);
+        assert_eq!(has_valid_field(&"ecl:gry"), true);
+        assert_eq!(has_valid_field(&"ecl:grn"), true);
+        assert_eq!(has_valid_field(&"ecl:hzl"), true);
+        assert_eq!(has_valid_field(&"ecl:oth"), true);
     }
 
     #[test]
-    pub fn test_pid_regex() {
+    pub fn test_pid_rule() {
         // pid (Passport ID) - a nine-digit number, including leading zeroes.
-        assert_eq!(has_valid_pid(&"pid:ads1"), false);
-        assert_eq!(has_valid_pid(&"asdr:amb"), false);
-        assert_eq!(has_valid_pid(&"pid:zzzzzzzzz"), false);
-        assert_eq!(has_valid_pid(&"pid:900000001"), true);
-        assert_eq!(has_valid_pid(&"pid:100000001"), true);
-        assert_eq!(has_valid_pid(&"pid:900000001"), true);
-        assert_eq!(has_valid_pid(&"pid:000000001"), true);
-        assert_eq!(has_valid_pid(&"pid:000000001"), true);
-        assert_eq!(has_valid_pid(&"pid:000000001"), true);
-        assert_eq!(has_valid_pid(&"pid:0000000001"), false);
+        assert_eq!(has_valid_field(&"pid:ads1"), false);
+        assert_eq!(has_valid_field(&"asdr:amb"), false);
+        assert_eq!(has_valid_field(&"pid:zzzzzzzzz"), false);
+        assert_eq!(has_valid_field(&"pid:900000001"), true);
+        assert_eq!(has_valid_field(&"pid:100000001"), true);
+        assert_eq!(has_valid_field(&"pid:900000001"), true);
+        assert_eq!(has_valid_field(&"pid:000000001"), true);
+        assert_eq!(has_valid_field(&"pid:000000001"), true);
+        assert_eq!(has_valid_field(&"pid:000000001"), true);
+        assert_eq!(has_valid_field(&"pid:0000000001"), false);
+    }
+
+    #[test]
+    pub fn test_parse_passports() {
+        let input = to_lines(
+            "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd\nbyr:1937 iyr:2017 cid:147 hgt:183cm\n\n\
+            iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884\nhcl:#cfa07d byr:1929\n",
+        );
+        let passports = parse_passports(&input);
+        assert_eq!(passports.len(), 2);
+        assert_eq!(passports[0].fields().len(), 8);
+        assert_eq!(passports[0].get("hgt"), Some("183cm"));
+        assert_eq!(passports[1].get("hgt"), None);
+        assert_eq!(passports[1].get("hcl"), Some("#cfa07d"));
+    }
+
+    #[test]
+    pub fn test_count_passports_with_all_fields() {
+        let input = to_lines(
+            "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd\nbyr:1937 iyr:2017 cid:147 hgt:183cm\n\n\
+            iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884\nhcl:#cfa07d byr:1929\n\n\
+            hcl:#ae17e1 iyr:2013\neyr:2024\necl:brn pid:760753108 byr:1931\nhgt:179cm\n\n\
+            hcl:#cfa07d eyr:2025 pid:166559648\niyr:2011 ecl:brn hgt:59in",
+        );
+        assert_eq!(count_passwords_with_all_fields(&input), 2);
+    }
+
+    #[test]
+    pub fn test_keys_inside_values_are_not_fields() {
+        // Missing hgt, it only shows up as a value.
+        let input = to_lines("ecl:brn pid:760753108 byr:1931 iyr:2013 eyr:2024 hcl:hgt");
+        assert_eq!(count_passwords_with_all_fields(&input), 0);
+    }
+
+    #[test]
+    pub fn test_count_valid_passports() {
+        let invalid = to_lines(
+            "eyr:1972 cid:100\nhcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:1926\n\n\
+            iyr:2019\nhcl:#602927 eyr:1967 hgt:170cm\necl:grn pid:012533040 byr:1946\n\n\
+            hcl:dab227 iyr:2012\necl:brn hgt:182cm pid:021572410 eyr:2020 byr:1992 cid:277\n\n\
+            hgt:59cm ecl:zzz\neyr:2038 hcl:74454a iyr:2023\npid:3556412378 byr:2007",
+        );
+        assert_eq!(count_valid_passwords(&invalid), 0);
+
+        let valid = to_lines(
+            "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980\nhcl:#623a2f\n\n\
+            eyr:2029 ecl:blu cid:129 byr:1989\niyr:2014 pid:896056539 hcl:#a97842 hgt:165cm\n\n\
+            hcl:#888785\nhgt:164cm byr:2001 iyr:2015 cid:88\npid:545766238 ecl:hzl\neyr:2022\n\n\
+            iyr:2010 hgt:158cm hcl:#b6652a ecl:blu byr:1944 eyr:2021 pid:093154719",
+        );
+        assert_eq!(count_valid_passwords(&valid), 4);
+    }
+
+    #[test]
+    pub fn test_custom_schema() {
+        let schema = Schema::parse(&to_lines(
+            "# Only a name and a short height are needed\n\
+            name required pattern [a-z]{2,5}\n\
+            hgt required measure 100-150cm\n\
+            byr optional year 1900 2000",
+        ));
+        assert_eq!(schema.fields().len(), 3);
+
+        let passports = parse_passports(&to_lines(
+            "name:ana hgt:120cm\n\nname:ana hgt:170cm\n\nname:ana hgt:120cm byr:2010\n\nhgt:120cm",
+        ));
+        let valid: Vec<bool> = passports
+            .iter()
+            .map(|passport| schema.is_valid(passport))
+            .collect();
+        assert_eq!(valid, vec![true, false, false, false]);
+    }
+
+    #[test]
+    pub fn test_validate_failures() {
+        let passports = parse_passports(&to_lines(
+            "eyr:1972 cid:100\nhcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:1926\n\n\
+            iyr:2019\nhcl:#602927 eyr:1967 hgt:170cm\necl:grn pid:012533040 byr:1946\n\n\
+            hcl:dab227 iyr:2012\necl:brn hgt:182cm pid:021572410 eyr:2020 byr:1992 cid:277\n\n\
+            hgt:59cm ecl:zzz\neyr:2038 hcl:74454a iyr:2023\npid:3556412378 byr:2007\n\n\
+            pid:087499704 hgt:74ft ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f byr:1981 xyz:1",
+        ));
+        let schema = puzzle_schema();
+        let failures: Vec<Vec<Failure>> = passports
+            .iter()
+            .map(|passport| schema.validate(passport))
+            .collect();
+        let invalid_value = |key: &str, error| Failure::InvalidValue(key.to_string(), error);
+        assert_eq!(
+            failures[0],
+            vec![
+                invalid_value("eyr", ValueError::OutOfRange),
+                invalid_value("hgt", ValueError::BadUnit),
+                invalid_value("pid", ValueError::BadFormat),
+            ]
+        );
+        assert_eq!(
+            failures[1],
+            vec![invalid_value("eyr", ValueError::OutOfRange)]
+        );
+        assert_eq!(
+            failures[2],
+            vec![invalid_value("hcl", ValueError::BadFormat)]
+        );
+        assert_eq!(
+            failures[3],
+            vec![
+                invalid_value("hgt", ValueError::OutOfRange),
+                invalid_value("ecl", ValueError::NotAllowed),
+                invalid_value("eyr", ValueError::OutOfRange),
+                invalid_value("hcl", ValueError::BadFormat),
+                invalid_value("iyr", ValueError::OutOfRange),
+                invalid_value("pid", ValueError::BadFormat),
+                invalid_value("byr", ValueError::OutOfRange),
+            ]
+        );
+        assert_eq!(
+            failures[4],
+            vec![
+                invalid_value("hgt", ValueError::BadUnit),
+                Failure::DuplicateField("byr".to_string()),
+                Failure::UnknownField("xyz".to_string()),
+            ]
+        );
+        assert_eq!(
+            schema.validate(&Passport::parse(&to_lines("hgt:170cm"))),
+            ["byr", "iyr", "eyr", "hcl", "ecl", "pid"]
+                .iter()
+                .map(|key| Failure::MissingField(key.to_string()))
+                .collect::<Vec<Failure>>()
+        );
+    }
+
+    #[test]
+    pub fn test_rejection_summary() {
+        let input = to_lines(
+            "iyr:2019\nhcl:#602927 eyr:1967 hgt:170cm\necl:grn pid:012533040 byr:1946\n\n\
+            hcl:dab227 iyr:2012\necl:brn hgt:182cm pid:021572410 eyr:2020 byr:1992 cid:277\n\n\
+            iyr:2010 hgt:158cm hcl:#b6652a ecl:blu byr:1944 eyr:2021 pid:093154719\n\n\
+            iyr:2010 hgt:158cm hcl:#b6652 ecl:blu byr:1944 eyr:2041 pid:093154719",
+        );
+        let groups = group_rejected_passports(&parse_passports(&input), puzzle_schema());
+        assert_eq!(groups.len(), 2);
+        assert_eq!(
+            groups[&Failure::InvalidValue("hcl".to_string(), ValueError::BadFormat)],
+            vec![1, 3]
+        );
+        assert_eq!(
+            groups[&Failure::InvalidValue("eyr".to_string(), ValueError::OutOfRange)],
+            vec![0, 3]
+        );
+        assert_eq!(
+            rejection_summary(&input, puzzle_schema()),
+            vec![
+                "3 of 4 passports rejected".to_string(),
+                "    2 eyr out of range".to_string(),
+                "    2 bad format in hcl".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_passport_serialisation() {
+        let passport = Passport::parse(&to_lines("hgt:183cm ecl:gry\nbyr:1937 cid:\"x"));
+        assert_eq!(passport.to_line(), "hgt:183cm ecl:gry byr:1937 cid:\"x");
+        assert_eq!(
+            passport.sorted().to_line(),
+            "byr:1937 cid:\"x ecl:gry hgt:183cm"
+        );
+        assert_eq!(
+            passport.sorted().to_json(),
+            r#"{"byr": "1937", "cid": "\"x", "ecl": "gry", "hgt": "183cm"}"#
+        );
+    }
+
+    #[test]
+    pub fn test_normalise_passports() {
+        let input = to_lines(
+            "eyr:1972 cid:100\nhcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:1926\n\n\n\
+            pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980\nhcl:#623a2f\n\n\
+            iyr:2010 hgt:158cm hcl:#b6652a ecl:blu byr:1944 eyr:2021 pid:093154719\n",
+        );
+        let schema = puzzle_schema();
+
+        let canonical = normalise_passports(&input, schema, BatchFormat::Canonical, Selection::All);
+        assert_eq!(
+            canonical,
+            vec![
+                "byr:1926 cid:100 ecl:amb eyr:1972 hcl:#18171d hgt:170 iyr:2018 pid:186cm",
+                "",
+                "byr:1980 ecl:grn eyr:2030 hcl:#623a2f hgt:74in iyr:2012 pid:087499704",
+                "",
+                "byr:1944 ecl:blu eyr:2021 hcl:#b6652a hgt:158cm iyr:2010 pid:093154719",
+            ]
+        );
+        // Canonical batches round-trip.
+        assert_eq!(
+            normalise_passports(&canonical, schema, BatchFormat::Canonical, Selection::All),
+            canonical
+        );
+        assert_eq!(
+            parse_passports(&canonical),
+            parse_passports(&input)
+                .iter()
+                .map(|passport| passport.sorted())
+                .collect::<Vec<Passport>>()
+        );
+
+        assert_eq!(
+            normalise_passports(&input, schema, BatchFormat::Canonical, Selection::Invalid),
+            vec!["byr:1926 cid:100 ecl:amb eyr:1972 hcl:#18171d hgt:170 iyr:2018 pid:186cm"]
+        );
+        assert_eq!(
+            normalise_passports(&input, schema, BatchFormat::Json, Selection::Valid),
+            vec![
+                "[",
+                r##"  {"byr": "1980", "ecl": "grn", "eyr": "2030", "hcl": "#623a2f", "hgt": "74in", "iyr": "2012", "pid": "087499704"},"##,
+                r##"  {"byr": "1944", "ecl": "blu", "eyr": "2021", "hcl": "#b6652a", "hgt": "158cm", "iyr": "2010", "pid": "093154719"}"##,
+                "]",
+            ]
+        );
+        assert_eq!(
+            normalise_passports(&[], schema, BatchFormat::Json, Selection::All),
+            vec!["[", "]"]
+        );
     }
 }
@@ -47,25 +47,168 @@
 // As a sanity check, look through your list of boarding passes. What is the highest seat ID on a
 // boarding pass?
 
-fn parse_boarding_pass(line: &str) -> (i32, i32) {
-    let mut row: i32 = 0;
-    let mut column: i32 = 0;
-    let mut chars = line.chars();
-    for i in 0..7 {
-        if chars.next().unwrap() == 'B' {
-            row += 2_i32.pow(6 - i)
+use std::fmt;
+
+/// Shape of the aircraft: how many bits encode the row and the column of a seat, and the letters
+/// picking the lower and upper half at each step.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Geometry {
+    row_bits: u32,
+    column_bits: u32,
+    row_letters: (char, char),
+    column_letters: (char, char),
+}
+
+impl Geometry {
+    /// 128 rows picked with F/B and 8 columns picked with L/R.
+    pub const PUZZLE: Geometry = Geometry {
+        row_bits: 7,
+        column_bits: 3,
+        row_letters: ('F', 'B'),
+        column_letters: ('L', 'R'),
+    };
+
+    /// Letters are given as (lower half, upper half). Seat IDs must fit in an u32, so there can be
+    /// at most 31 bits between rows and columns.
+    pub fn new(
+        row_bits: u32,
+        column_bits: u32,
+        row_letters: (char, char),
+        column_letters: (char, char),
+    ) -> Geometry {
+        if row_bits + column_bits > 31 {
+            panic!("Too many bits: {} + {}", row_bits, column_bits);
+        }
+        if row_letters.0 == row_letters.1 || column_letters.0 == column_letters.1 {
+            panic!("Ambiguous letters {:?} {:?}", row_letters, column_letters);
+        }
+        Geometry {
+            row_bits,
+            column_bits,
+            row_letters,
+            column_letters,
+        }
+    }
+
+    pub fn rows(&self) -> u32 {
+        1 << self.row_bits
+    }
+
+    pub fn columns(&self) -> u32 {
+        1 << self.column_bits
+    }
+
+    pub fn seats(&self) -> u32 {
+        self.rows() * self.columns()
+    }
+
+    /// Length of a boarding pass code.
+    pub fn code_len(&self) -> usize {
+        (self.row_bits + self.column_bits) as usize
+    }
+}
+
+/// A seat on an aircraft. It converts between the boarding pass code, the (row, column) pair and
+/// the seat ID, in every direction.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct BoardingPass {
+    row: u32,
+    column: u32,
+    geometry: Geometry,
+}
+
+impl BoardingPass {
+    /// None if the seat is not on the aircraft.
+    pub fn new(row: u32, column: u32, geometry: Geometry) -> Option<BoardingPass> {
+        if row < geometry.rows() && column < geometry.columns() {
+            Some(BoardingPass {
+                row,
+                column,
+                geometry,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// None if the seat is not on the aircraft.
+    pub fn from_seat_id(seat_id: u32, geometry: Geometry) -> Option<BoardingPass> {
+        BoardingPass::new(
+            seat_id / geometry.columns(),
+            seat_id % geometry.columns(),
+            geometry,
+        )
+    }
+
+    /// Decodes a code like FBFBBFFRLR: each letter keeps the lower or upper half of the remaining
+    /// rows or columns, which is just reading the letters as bits. None if the code has the wrong
+    /// length or unexpected letters.
+    pub fn decode(code: &str, geometry: Geometry) -> Option<BoardingPass> {
+        fn read_bits(letters: &[char], (lower, upper): (char, char)) -> Option<u32> {
+            letters.iter().try_fold(0, |value, letter| match *letter {
+                letter if letter == lower => Some(value << 1),
+                letter if letter == upper => Some(value << 1 | 1),
+                _ => None,
+            })
         }
+
+        let letters: Vec<char> = code.chars().collect();
+        if letters.len() != geometry.code_len() {
+            return None;
+        }
+        let (row_letters, column_letters) = letters.split_at(geometry.row_bits as usize);
+        BoardingPass::new(
+            read_bits(row_letters, geometry.row_letters)?,
+            read_bits(column_letters, geometry.column_letters)?,
+            geometry,
+        )
     }
-    for i in 0..3 {
-        if chars.next().unwrap() == 'R' {
-            column += 2_i32.pow(2 - i)
+
+    pub fn encode(&self) -> String {
+        fn write_bits(value: u32, bits: u32, (lower, upper): (char, char)) -> String {
+            (0..bits)
+                .rev()
+                .map(|bit| if value >> bit & 1 == 1 { upper } else { lower })
+                .collect()
         }
+
+        let geometry = &self.geometry;
+        write_bits(self.row, geometry.row_bits, geometry.row_letters)
+            + &write_bits(self.column, geometry.column_bits, geometry.column_letters)
+    }
+
+    pub fn row(&self) -> u32 {
+        self.row
+    }
+
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+
+    pub fn geometry(&self) -> Geometry {
+        self.geometry
+    }
+
+    /// Multiply the row by the number of columns, then add the column.
+    pub fn seat_id(&self) -> u32 {
+        self.row * self.geometry.columns() + self.column
+    }
+}
+
+impl fmt::Display for BoardingPass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.encode())
     }
-    (row, column)
+}
+
+fn parse_boarding_pass(line: &str) -> (i32, i32) {
+    let boarding_pass = BoardingPass::decode(line, Geometry::PUZZLE)
+        .unwrap_or_else(|| panic!("Unexpected boarding pass: {}", line));
+    (boarding_pass.row() as i32, boarding_pass.column() as i32)
 }
 
 fn id(row: i32, column: i32) -> i32 {
-    row * 8 + column
+    row * Geometry::PUZZLE.columns() as i32 + column
 }
 
 pub fn find_highest_id(lines: &[String]) -> i32 {
@@ -89,21 +232,122 @@ pub fn find_highest_id(lines: &[String]) -> i32 {
 //
 // What is the ID of your seat?
 
-pub fn find_seat_id(lines: &[String]) -> i32 {
-    let existing_ids: Vec<i32> = lines
-        .iter()
-        .map(|line| parse_boarding_pass(line))
-        .map(|(row, column)| id(row, column))
-        .collect();
+/// Boarding passes seen for every seat of an aircraft, indexed by seat ID.
+#[derive(PartialEq, Debug, Clone)]
+pub struct SeatMap {
+    geometry: Geometry,
+    passes_per_seat: Vec<u32>,
+}
 
-    let min = existing_ids.iter().min().unwrap();
-    let max = existing_ids.iter().max().unwrap();
-    for i in (*min + 1)..*max {
-        if !existing_ids.contains(&i) {
-            return i;
+impl SeatMap {
+    pub fn new(boarding_passes: &[BoardingPass], geometry: Geometry) -> SeatMap {
+        let mut passes_per_seat = vec![0; geometry.seats() as usize];
+        for boarding_pass in boarding_passes {
+            if boarding_pass.geometry() != geometry {
+                panic!("Boarding pass {} from another aircraft", boarding_pass);
+            }
+            passes_per_seat[boarding_pass.seat_id() as usize] += 1;
         }
+        SeatMap {
+            geometry,
+            passes_per_seat,
+        }
+    }
+
+    pub fn parse(lines: &[String], geometry: Geometry) -> SeatMap {
+        let boarding_passes: Vec<BoardingPass> = lines
+            .iter()
+            .map(|line| {
+                BoardingPass::decode(line, geometry)
+                    .unwrap_or_else(|| panic!("Unexpected boarding pass: {}", line))
+            })
+            .collect();
+        SeatMap::new(&boarding_passes, geometry)
+    }
+
+    pub fn is_occupied(&self, seat_id: u32) -> bool {
+        self.passes_per_seat
+            .get(seat_id as usize)
+            .is_some_and(|passes| *passes > 0)
+    }
+
+    fn is_row_empty(&self, row: u32) -> bool {
+        let columns = self.geometry.columns();
+        (row * columns..(row + 1) * columns).all(|seat_id| !self.is_occupied(seat_id))
+    }
+
+    /// Empty seats whose IDs +1 and -1 are both occupied.
+    pub fn empty_seats_between_occupied(&self) -> Vec<u32> {
+        (1..self.geometry.seats().saturating_sub(1))
+            .filter(|seat_id| {
+                !self.is_occupied(*seat_id)
+                    && self.is_occupied(seat_id - 1)
+                    && self.is_occupied(seat_id + 1)
+            })
+            .collect()
+    }
+
+    /// Rows at the very front without a single boarding pass. All rows if the map is empty.
+    pub fn missing_front_rows(&self) -> Vec<u32> {
+        (0..self.geometry.rows())
+            .take_while(|row| self.is_row_empty(*row))
+            .collect()
+    }
+
+    /// Rows at the very back without a single boarding pass, front to back. All rows if the map
+    /// is empty.
+    pub fn missing_back_rows(&self) -> Vec<u32> {
+        let mut rows: Vec<u32> = (0..self.geometry.rows())
+            .rev()
+            .take_while(|row| self.is_row_empty(*row))
+            .collect();
+        rows.reverse();
+        rows
+    }
+
+    /// Seats with more than one boarding pass, with the number of passes found for each.
+    pub fn duplicated_seats(&self) -> Vec<(BoardingPass, u32)> {
+        self.passes_per_seat
+            .iter()
+            .enumerate()
+            .filter(|(_, passes)| **passes > 1)
+            .map(|(seat_id, passes)| {
+                (
+                    BoardingPass::from_seat_id(seat_id as u32, self.geometry).unwrap(),
+                    *passes,
+                )
+            })
+            .collect()
+    }
+
+    /// One line per row, front first: the row number followed by a `#` for each occupied seat, a
+    /// `.` for each empty seat and a `!` for each seat with several boarding passes.
+    pub fn render(&self) -> Vec<String> {
+        let row_label_width = (self.geometry.rows() - 1).to_string().len();
+        self.passes_per_seat
+            .chunks(self.geometry.columns() as usize)
+            .enumerate()
+            .map(|(row, seats)| {
+                let seats: String = seats
+                    .iter()
+                    .map(|passes| match passes {
+                        0 => '.',
+                        1 => '#',
+                        _ => '!',
+                    })
+                    .collect();
+                format!("{:>width$} {}", row, seats, width = row_label_width)
+            })
+            .collect()
+    }
+}
+
+pub fn find_seat_id(lines: &[String]) -> i32 {
+    let seat_map = SeatMap::parse(lines, Geometry::PUZZLE);
+    match seat_map.empty_seats_between_occupied().first() {
+        Some(seat_id) => *seat_id as i32,
+        None => panic!("Seat it not found"),
     }
-    panic!("Seat it not found");
 }
 
 #[cfg(test)]
@@ -131,4 +375,111 @@ mod tests {
         // BBFFBBFRLL: row 102, column 4, seat ID 820.
         assert_eq!(id(102, 4), 820);
     }
+
+    #[test]
+    pub fn test_boarding_pass_conversions() {
+        let boarding_pass = BoardingPass::decode("FBFBBFFRLR", Geometry::PUZZLE).unwrap();
+        assert_eq!((boarding_pass.row(), boarding_pass.column()), (44, 5));
+        assert_eq!(boarding_pass.seat_id(), 357);
+        assert_eq!(boarding_pass.encode(), "FBFBBFFRLR");
+        assert_eq!(boarding_pass.to_string(), "FBFBBFFRLR");
+        assert_eq!(
+            BoardingPass::from_seat_id(357, Geometry::PUZZLE),
+            Some(boarding_pass)
+        );
+        assert_eq!(
+            BoardingPass::new(44, 5, Geometry::PUZZLE),
+            Some(boarding_pass)
+        );
+        assert_eq!(
+            BoardingPass::from_seat_id(820, Geometry::PUZZLE).map(|pass| pass.encode()),
+            Some("BBFFBBFRLL".to_string())
+        );
+
+        for seat_id in 0..Geometry::PUZZLE.seats() {
+            let boarding_pass = BoardingPass::from_seat_id(seat_id, Geometry::PUZZLE).unwrap();
+            let decoded = BoardingPass::decode(&boarding_pass.encode(), Geometry::PUZZLE);
+            assert_eq!(decoded.map(|pass| pass.seat_id()), Some(seat_id));
+        }
+    }
+
+    #[test]
+    pub fn test_invalid_boarding_passes() {
+        assert_eq!(BoardingPass::decode("FBFBBFFRL", Geometry::PUZZLE), None);
+        assert_eq!(BoardingPass::decode("FBFBBFFRLRR", Geometry::PUZZLE), None);
+        assert_eq!(BoardingPass::decode("FBFBBFLRLR", Geometry::PUZZLE), None);
+        assert_eq!(BoardingPass::decode("FBFBBFFRLB", Geometry::PUZZLE), None);
+        assert_eq!(BoardingPass::new(128, 0, Geometry::PUZZLE), None);
+        assert_eq!(BoardingPass::new(0, 8, Geometry::PUZZLE), None);
+        assert_eq!(BoardingPass::from_seat_id(1024, Geometry::PUZZLE), None);
+    }
+
+    #[test]
+    pub fn test_custom_geometry() {
+        // 16 rows picked with 0/1 and 4 columns picked with a/b.
+        let geometry = Geometry::new(4, 2, ('0', '1'), ('a', 'b'));
+        assert_eq!(geometry.seats(), 64);
+        let boarding_pass = BoardingPass::decode("1010ba", geometry).unwrap();
+        assert_eq!((boarding_pass.row(), boarding_pass.column()), (10, 2));
+        assert_eq!(boarding_pass.seat_id(), 42);
+        assert_eq!(
+            BoardingPass::from_seat_id(42, geometry)
+                .unwrap()
+                .to_string(),
+            "1010ba"
+        );
+        assert_eq!(BoardingPass::decode("FBFBBFFRLR", geometry), None);
+    }
+
+    fn seat_map(seat_ids: &[u32]) -> SeatMap {
+        let boarding_passes: Vec<BoardingPass> = seat_ids
+            .iter()
+            .map(|seat_id| BoardingPass::from_seat_id(*seat_id, Geometry::PUZZLE).unwrap())
+            .collect();
+        SeatMap::new(&boarding_passes, Geometry::PUZZLE)
+    }
+
+    #[test]
+    pub fn test_seat_map_gaps() {
+        // Rows 0-1 and 125-127 missing, a gap at 30 and a run of empty seats 40-41.
+        let mut seat_ids: Vec<u32> = (16..1000)
+            .filter(|seat_id| ![30, 40, 41].contains(seat_id))
+            .collect();
+        seat_ids.push(500);
+        seat_ids.push(500);
+        seat_ids.push(17);
+        let seat_map = seat_map(&seat_ids);
+        assert_eq!(seat_map.empty_seats_between_occupied(), vec![30]);
+        assert_eq!(seat_map.missing_front_rows(), vec![0, 1]);
+        assert_eq!(seat_map.missing_back_rows(), vec![125, 126, 127]);
+        assert_eq!(
+            seat_map
+                .duplicated_seats()
+                .iter()
+                .map(|(boarding_pass, passes)| (boarding_pass.seat_id(), *passes))
+                .collect::<Vec<(u32, u32)>>(),
+            vec![(17, 2), (500, 3)]
+        );
+        assert!(seat_map.is_occupied(16));
+        assert!(!seat_map.is_occupied(15));
+        assert!(!seat_map.is_occupied(1024));
+    }
+
+    #[test]
+    pub fn test_empty_seat_map() {
+        let seat_map = seat_map(&[]);
+        assert_eq!(seat_map.empty_seats_between_occupied(), vec![]);
+        assert_eq!(seat_map.missing_front_rows().len(), 128);
+        assert_eq!(seat_map.missing_back_rows().len(), 128);
+    }
+
+    #[test]
+    pub fn test_render_seat_map() {
+        let seat_map = seat_map(&[0, 2, 2, 15]);
+        let rendered = seat_map.render();
+        assert_eq!(rendered.len(), 128);
+        assert_eq!(rendered[0], "  0 #.!.....");
+        assert_eq!(rendered[1], "  1 .......#");
+        assert_eq!(rendered[127], "127 ........");
+    }
 }
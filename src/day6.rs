@@ -38,22 +38,122 @@
 //
 // For each group, count the number of questions to which anyone answered "yes". What is the sum of those counts?
 
-use reduce::Reduce;
-use std::collections::HashSet;
-use std::iter::FromIterator;
+const QUESTIONS: usize = 26;
 
-pub fn count_number_of_unique_group_yes_answers(lines: &[String]) -> usize {
-    return lines
+/// Questions a person answered "yes" to, bit `i` set for question `'a' + i`.
+fn parse_answers(line: &str) -> u32 {
+    line.chars().fold(0, |answers, char| match char {
+        'a'..='z' => answers | 1 << (char as u32 - 'a' as u32),
+        _ => panic!("Unexpected char {}", char),
+    })
+}
+
+/// Question letters in a set of answers, alphabetically.
+pub fn questions(answers: u32) -> Vec<char> {
+    (0..QUESTIONS as u32)
+        .filter(|bit| answers >> bit & 1 == 1)
+        .map(|bit| (b'a' + bit as u8) as char)
+        .collect()
+}
+
+/// Answers of a group, one set of answers per person.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Group {
+    answers: Vec<u32>,
+}
+
+impl Group {
+    pub fn parse(lines: &[String]) -> Group {
+        Group {
+            answers: lines.iter().map(|line| parse_answers(line)).collect(),
+        }
+    }
+
+    pub fn people(&self) -> usize {
+        self.answers.len()
+    }
+
+    /// How many people answered "yes" to each question, `a` first.
+    pub fn tallies(&self) -> [usize; QUESTIONS] {
+        let mut tallies = [0; QUESTIONS];
+        for answers in &self.answers {
+            for (bit, tally) in tallies.iter_mut().enumerate() {
+                *tally += (answers >> bit & 1) as usize;
+            }
+        }
+        tallies
+    }
+
+    /// Questions answered "yes" by at least `quorum` people. A quorum of 0 gives every question.
+    pub fn answered_by_at_least(&self, quorum: usize) -> u32 {
+        self.tallies()
+            .iter()
+            .enumerate()
+            .filter(|(_, tally)| **tally >= quorum)
+            .fold(0, |answers, (bit, _)| answers | 1 << bit)
+    }
+
+    /// Questions anyone answered "yes" to.
+    pub fn anyone(&self) -> u32 {
+        self.answered_by_at_least(1)
+    }
+
+    /// Questions everyone answered "yes" to.
+    pub fn everyone(&self) -> u32 {
+        self.answered_by_at_least(self.people())
+    }
+
+    /// Questions with the most "yes" answers, alphabetically. Empty if nobody answered "yes".
+    pub fn most_popular(&self) -> Vec<char> {
+        let tallies = self.tallies();
+        match tallies.iter().max() {
+            Some(max) if *max > 0 => self.questions_with_tally(&tallies, *max),
+            _ => vec![],
+        }
+    }
+
+    /// Questions with the fewest "yes" answers among those anyone answered "yes" to,
+    /// alphabetically. Empty if nobody answered "yes".
+    pub fn least_popular(&self) -> Vec<char> {
+        let tallies = self.tallies();
+        match tallies.iter().filter(|tally| **tally > 0).min() {
+            Some(min) => self.questions_with_tally(&tallies, *min),
+            None => vec![],
+        }
+    }
+
+    fn questions_with_tally(&self, tallies: &[usize; QUESTIONS], tally: usize) -> Vec<char> {
+        let answers = tallies
+            .iter()
+            .enumerate()
+            .filter(|(_, question_tally)| **question_tally == tally)
+            .fold(0, |answers, (bit, _)| answers | 1 << bit);
+        questions(answers)
+    }
+}
+
+/// Groups are separated by blank lines, with one person per line.
+pub fn parse_groups(lines: &[String]) -> Vec<Group> {
+    lines
         .split(|line| line.is_empty())
-        .map(|group| {
-            let yes_answers = group
-                .iter()
-                .flat_map(|person| person.chars().collect::<Vec<char>>())
-                .collect::<Vec<char>>();
-            let unique_yes_answers = HashSet::<char>::from_iter(yes_answers);
-            unique_yes_answers.len()
-        })
-        .sum();
+        .filter(|group| !group.is_empty())
+        .map(Group::parse)
+        .collect()
+}
+
+/// Sum over all groups of the questions answered "yes" by at least `quorum` people of the group.
+pub fn count_answered_by_at_least(lines: &[String], quorum: usize) -> usize {
+    parse_groups(lines)
+        .iter()
+        .map(|group| group.answered_by_at_least(quorum).count_ones() as usize)
+        .sum()
+}
+
+pub fn count_number_of_unique_group_yes_answers(lines: &[String]) -> usize {
+    parse_groups(lines)
+        .iter()
+        .map(|group| group.anyone().count_ones() as usize)
+        .sum()
 }
 
 // --- Part Two ---
@@ -93,15 +193,67 @@ pub fn count_number_of_unique_group_yes_answers(lines: &[String]) -> usize {
 // For each group, count the number of questions to which everyone answered "yes". What is the sum
 // of those counts?
 pub fn count_number_of_unanimous_group_yes_answers(lines: &[String]) -> usize {
-    return lines
-        .split(|line| line.is_empty())
-        .map(|group| {
-            group
-                .iter()
-                .map::<HashSet<char>, _>(|line| line.chars().collect())
-                .reduce(|a, b| a.intersection(&b).cloned().collect())
-                .unwrap()
-                .len()
-        })
-        .sum();
+    parse_groups(lines)
+        .iter()
+        .map(|group| group.everyone().count_ones() as usize)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Vec<String> {
+        "abc\n\na\nb\nc\n\nab\nac\n\na\na\na\na\n\nb"
+            .lines()
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    #[test]
+    pub fn test_parse_answers() {
+        assert_eq!(parse_answers(""), 0);
+        assert_eq!(parse_answers("abc"), 0b111);
+        assert_eq!(parse_answers("za"), 1 << 25 | 1);
+        assert_eq!(questions(parse_answers("xbay")), vec!['a', 'b', 'x', 'y']);
+    }
+
+    #[test]
+    pub fn test_anyone_and_everyone() {
+        assert_eq!(count_number_of_unique_group_yes_answers(&example()), 11);
+        assert_eq!(count_number_of_unanimous_group_yes_answers(&example()), 6);
+    }
+
+    #[test]
+    pub fn test_quorum() {
+        let group = Group::parse(&["abcx".to_string(), "abcy".to_string(), "abz".to_string()]);
+        assert_eq!(group.people(), 3);
+        assert_eq!(questions(group.answered_by_at_least(0)).len(), 26);
+        assert_eq!(
+            questions(group.answered_by_at_least(1)),
+            vec!['a', 'b', 'c', 'x', 'y', 'z']
+        );
+        assert_eq!(
+            questions(group.answered_by_at_least(2)),
+            vec!['a', 'b', 'c']
+        );
+        assert_eq!(questions(group.answered_by_at_least(3)), vec!['a', 'b']);
+        assert_eq!(group.answered_by_at_least(4), 0);
+        assert_eq!(count_answered_by_at_least(&example(), 2), 2);
+    }
+
+    #[test]
+    pub fn test_tallies_and_popularity() {
+        let group = Group::parse(&["abcx".to_string(), "abcy".to_string(), "abz".to_string()]);
+        let tallies = group.tallies();
+        assert_eq!(&tallies[0..3], &[3, 3, 2]);
+        assert_eq!(&tallies[23..26], &[1, 1, 1]);
+        assert_eq!(group.most_popular(), vec!['a', 'b']);
+        assert_eq!(group.least_popular(), vec!['x', 'y', 'z']);
+
+        let silent_group = Group::parse(&["".to_string()]);
+        assert!(silent_group.most_popular().is_empty());
+        assert!(silent_group.least_popular().is_empty());
+        assert_eq!(silent_group.everyone(), 0);
+    }
 }
@@ -39,9 +39,12 @@
 //
 // How many bag colors can eventually contain at least one shiny gold bag? (The list of rules is
 // quite long; make sure you get all of it.)
+use crate::big_uint::BigUint;
+use crate::output_utils::{dot_string, json_string};
 use lazy_static::lazy_static;
 use regex::{Captures, Regex};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
 
 type InnerBagsRules = Vec<(String, usize)>;
 
@@ -74,39 +77,536 @@ fn parse_bag_rules(lines: &[String]) -> HashMap<String, InnerBagsRules> {
     rules
 }
 
-pub fn count_bags_containing_shiny_gold(lines: &[String]) -> usize {
-    let rules = parse_bag_rules(lines);
-
-    // Cache of already checked bags
-    let mut cache = HashMap::<String, bool>::new();
-
-    fn contains_shiny_gold(
-        rules: &HashMap<String, InnerBagsRules>,
-        contains: &mut HashMap<String, bool>,
-        bag_color: &str,
-    ) -> bool {
-        return contains.get(bag_color).copied().unwrap_or_else(|| {
-            for (inner_color, _) in rules.get(bag_color).unwrap().iter() {
-                if inner_color == "shiny gold" || contains_shiny_gold(rules, contains, inner_color)
-                {
-                    contains.insert(bag_color.to_string(), true);
-                    return true;
+/// Why a query on the bag rules can't be answered.
+#[derive(PartialEq, Debug, Clone)]
+pub enum BagError {
+    /// The color doesn't show up in any rule.
+    UnknownColor(String),
+    /// The color is referenced by a rule, but there is no rule for it.
+    DanglingColor(String),
+    /// The bags can contain themselves, so they hold infinitely many bags. The path starts and
+    /// ends with the same color.
+    Cycle(Vec<String>),
+    /// The number of bags inside the color doesn't fit in the type used to count them.
+    Overflow(String),
+}
+
+impl fmt::Display for BagError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BagError::UnknownColor(color) => write!(f, "unknown color {}", color),
+            BagError::DanglingColor(color) => write!(f, "no rule for {} bags", color),
+            BagError::Cycle(path) => write!(f, "cycle {}", path.join(" -> ")),
+            BagError::Overflow(color) => write!(f, "too many bags inside {}", color),
+        }
+    }
+}
+
+/// Problems found in a set of bag rules.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Validation {
+    /// One cycle per back edge found by a depth first search, each starting and ending with the
+    /// same color.
+    pub cycles: Vec<Vec<String>>,
+    /// (outer color, inner color) for each rule referencing a color that has no rule.
+    pub dangling: Vec<(String, String)>,
+    /// Colors that can neither contain nor be contained by the color the rules were validated
+    /// for, sorted by name.
+    pub unreachable: Vec<String>,
+}
+
+impl Validation {
+    /// Unreachable colors don't change any answer, so they don't make the rules invalid.
+    pub fn is_valid(&self) -> bool {
+        self.cycles.is_empty() && self.dangling.is_empty()
+    }
+}
+
+/// Numbers bags can be counted with.
+pub trait BagCount: Clone {
+    fn from_usize(value: usize) -> Self;
+    /// None on overflow.
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    /// None on overflow.
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+}
+
+impl BagCount for usize {
+    fn from_usize(value: usize) -> usize {
+        value
+    }
+
+    fn checked_add(&self, other: &usize) -> Option<usize> {
+        usize::checked_add(*self, *other)
+    }
+
+    fn checked_mul(&self, other: &usize) -> Option<usize> {
+        usize::checked_mul(*self, *other)
+    }
+}
+
+impl BagCount for BigUint {
+    fn from_usize(value: usize) -> BigUint {
+        BigUint::from(value)
+    }
+
+    fn checked_add(&self, other: &BigUint) -> Option<BigUint> {
+        Some(self + other)
+    }
+
+    fn checked_mul(&self, other: &BigUint) -> Option<BigUint> {
+        Some(self * other)
+    }
+}
+
+/// Part of the bag graph to export.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Subgraph {
+    All,
+    /// The color and every bag that can end up inside it.
+    From(String),
+    /// The color and every bag that can eventually contain it.
+    To(String),
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum Visit {
+    New,
+    InProgress,
+    Done,
+}
+
+/// Bag rules as a graph, with an edge from each bag to every bag it directly contains weighted
+/// with the quantity, plus the reverse edges to find the bags a given bag can be in. Colors
+/// referenced by a rule are part of the graph even if they have no rule of their own.
+#[derive(PartialEq, Debug, Clone)]
+pub struct BagGraph {
+    colors: Vec<String>,
+    indices: HashMap<String, usize>,
+    has_rule: Vec<bool>,
+    children: Vec<Vec<(usize, usize)>>,
+    parents: Vec<Vec<(usize, usize)>>,
+}
+
+impl BagGraph {
+    pub fn from_rules(rules: &HashMap<String, InnerBagsRules>) -> BagGraph {
+        let mut graph = BagGraph {
+            colors: Vec::new(),
+            indices: HashMap::new(),
+            has_rule: Vec::new(),
+            children: Vec::new(),
+            parents: Vec::new(),
+        };
+        // Sorted so the graph doesn't depend on the hash map order.
+        let mut outer_colors: Vec<&String> = rules.keys().collect();
+        outer_colors.sort();
+        for color in &outer_colors {
+            let outer = graph.add_color(color);
+            graph.has_rule[outer] = true;
+        }
+        for color in &outer_colors {
+            let outer = graph.indices[*color];
+            for (inner_color, quantity) in &rules[*color] {
+                let inner = graph.add_color(inner_color);
+                graph.children[outer].push((inner, *quantity));
+                graph.parents[inner].push((outer, *quantity));
+            }
+        }
+        graph
+    }
+
+    pub fn parse(lines: &[String]) -> BagGraph {
+        BagGraph::from_rules(&parse_bag_rules(lines))
+    }
+
+    fn add_color(&mut self, color: &str) -> usize {
+        if let Some(index) = self.indices.get(color) {
+            return *index;
+        }
+        let index = self.colors.len();
+        self.colors.push(color.to_string());
+        self.indices.insert(color.to_string(), index);
+        self.has_rule.push(false);
+        self.children.push(Vec::new());
+        self.parents.push(Vec::new());
+        index
+    }
+
+    fn index(&self, color: &str) -> Option<usize> {
+        self.indices.get(color).copied()
+    }
+
+    fn index_or_error(&self, color: &str) -> Result<usize, BagError> {
+        self.index(color)
+            .ok_or_else(|| BagError::UnknownColor(color.to_string()))
+    }
+
+    fn named(&self, edges: &[(usize, usize)]) -> Vec<(&str, usize)> {
+        edges
+            .iter()
+            .map(|(index, quantity)| (self.colors[*index].as_str(), *quantity))
+            .collect()
+    }
+
+    fn names(&self, indices: &[usize]) -> Vec<String> {
+        indices
+            .iter()
+            .map(|index| self.colors[*index].clone())
+            .collect()
+    }
+
+    pub fn colors(&self) -> &[String] {
+        &self.colors
+    }
+
+    pub fn contains_color(&self, color: &str) -> bool {
+        self.indices.contains_key(color)
+    }
+
+    /// Bags directly inside the given bag, with their quantities, in rule order.
+    pub fn children(&self, color: &str) -> Vec<(&str, usize)> {
+        self.index(color)
+            .map_or_else(Vec::new, |index| self.named(&self.children[index]))
+    }
+
+    /// Bags that directly contain the given bag, with the quantity they hold.
+    pub fn parents(&self, color: &str) -> Vec<(&str, usize)> {
+        self.index(color)
+            .map_or_else(Vec::new, |index| self.named(&self.parents[index]))
+    }
+
+    fn reachable(&self, start: usize, edges: &[Vec<(usize, usize)>]) -> Vec<bool> {
+        let mut visited = vec![false; self.colors.len()];
+        let mut pending = vec![start];
+        while let Some(index) = pending.pop() {
+            for (next, _) in &edges[index] {
+                if !visited[*next] {
+                    visited[*next] = true;
+                    pending.push(*next);
+                }
+            }
+        }
+        visited
+    }
+
+    /// Colors that can eventually contain at least one bag of the given color, sorted by name.
+    pub fn ancestors(&self, color: &str) -> Vec<&str> {
+        let visited = match self.index(color) {
+            Some(index) => self.reachable(index, &self.parents),
+            None => return Vec::new(),
+        };
+        let mut ancestors: Vec<&str> = (0..self.colors.len())
+            .filter(|index| visited[*index])
+            .map(|index| self.colors[index].as_str())
+            .collect();
+        ancestors.sort_unstable();
+        ancestors
+    }
+
+    /// Depth first search over the bags inside the given ones, without recursion so deep rule
+    /// sets can't overflow the stack. Returns the bags reached in post-order, every bag after all
+    /// the bags inside it, and the cycles closed by each back edge.
+    fn depth_first(&self, starts: &[usize]) -> (Vec<usize>, Vec<Vec<usize>>) {
+        let mut visits = vec![Visit::New; self.colors.len()];
+        let mut order = Vec::new();
+        let mut cycles = Vec::new();
+        for start in starts {
+            if visits[*start] != Visit::New {
+                continue;
+            }
+            // Bags being explored with the position of the next child to look at.
+            let mut stack = vec![(*start, 0)];
+            visits[*start] = Visit::InProgress;
+            while let Some((index, next_child)) = stack.last_mut() {
+                let index = *index;
+                match self.children[index].get(*next_child) {
+                    Some((child, _)) => {
+                        *next_child += 1;
+                        match visits[*child] {
+                            Visit::New => {
+                                visits[*child] = Visit::InProgress;
+                                stack.push((*child, 0));
+                            }
+                            Visit::InProgress => {
+                                let from = stack.iter().position(|(i, _)| i == child).unwrap();
+                                let mut cycle: Vec<usize> =
+                                    stack[from..].iter().map(|(i, _)| *i).collect();
+                                cycle.push(*child);
+                                cycles.push(cycle);
+                            }
+                            Visit::Done => {}
+                        }
+                    }
+                    None => {
+                        visits[index] = Visit::Done;
+                        order.push(index);
+                        stack.pop();
+                    }
+                }
+            }
+        }
+        (order, cycles)
+    }
+
+    /// Post-order of the bags inside the given one, or the first cycle found.
+    fn acyclic_order(&self, start: usize) -> Result<Vec<usize>, BagError> {
+        let (order, cycles) = self.depth_first(&[start]);
+        match cycles.first() {
+            Some(cycle) => Err(BagError::Cycle(self.names(cycle))),
+            None => Ok(order),
+        }
+    }
+
+    /// Number of individual bags required inside a single bag of the given color, or an overflow
+    /// error if it doesn't fit in an usize.
+    pub fn total_contents(&self, color: &str) -> Result<usize, BagError> {
+        self.total_contents_as::<usize>(color)
+    }
+
+    /// Number of individual bags required inside a single bag of the given color, however big.
+    pub fn total_contents_big(&self, color: &str) -> Result<BigUint, BagError> {
+        self.total_contents_as::<BigUint>(color)
+    }
+
+    /// Number of individual bags required inside a single bag of the given color, counted with
+    /// any BagCount type. Each bag's capacity is computed once and reused by every bag holding it.
+    pub fn total_contents_as<T: BagCount>(&self, color: &str) -> Result<T, BagError> {
+        let start = self.index_or_error(color)?;
+        let order = self.acyclic_order(start)?;
+        let overflow = || BagError::Overflow(color.to_string());
+
+        // Capacity of every bag, filled in post-order so the inner bags are always known.
+        let mut capacities: Vec<Option<T>> = vec![None; self.colors.len()];
+        for index in order {
+            if !self.has_rule[index] {
+                return Err(BagError::DanglingColor(self.colors[index].clone()));
+            }
+            let mut capacity = T::from_usize(0);
+            for (inner, quantity) in &self.children[index] {
+                let inner_capacity = capacities[*inner].as_ref().unwrap();
+                // Add the (bag iself + the inner bags) * number of times
+                let bags = T::from_usize(1)
+                    .checked_add(inner_capacity)
+                    .and_then(|bags| bags.checked_mul(&T::from_usize(*quantity)))
+                    .ok_or_else(overflow)?;
+                capacity = capacity.checked_add(&bags).ok_or_else(overflow)?;
+            }
+            capacities[index] = Some(capacity);
+        }
+        Ok(capacities[start].take().unwrap())
+    }
+
+    /// Chain of bags with the fewest steps going from the outer bag down to the inner one, both
+    /// included. None if the outer bag can't contain the inner one.
+    pub fn shortest_path(&self, outer: &str, inner: &str) -> Result<Option<Vec<&str>>, BagError> {
+        let start = self.index_or_error(outer)?;
+        let target = self.index_or_error(inner)?;
+
+        // Breadth first search, remembering where each bag was reached from.
+        let mut reached_from: Vec<Option<usize>> = vec![None; self.colors.len()];
+        let mut visited = vec![false; self.colors.len()];
+        visited[start] = true;
+        let mut queue = VecDeque::from(vec![start]);
+        while let Some(index) = queue.pop_front() {
+            if index == target {
+                return Ok(Some(self.path_to(index, &reached_from)));
+            }
+            for (child, _) in &self.children[index] {
+                if !visited[*child] {
+                    visited[*child] = true;
+                    reached_from[*child] = Some(index);
+                    queue.push_back(*child);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn path_to(&self, index: usize, reached_from: &[Option<usize>]) -> Vec<&str> {
+        let mut path = vec![self.colors[index].as_str()];
+        let mut index = index;
+        while let Some(previous) = reached_from[index] {
+            path.push(self.colors[previous].as_str());
+            index = previous;
+        }
+        path.reverse();
+        path
+    }
+
+    /// Chain of bags with the most steps going from the outer bag down to the inner one, both
+    /// included. None if the outer bag can't contain the inner one. Any cycle inside the outer bag
+    /// is an error, as paths could be made as long as wanted.
+    pub fn longest_path(&self, outer: &str, inner: &str) -> Result<Option<Vec<&str>>, BagError> {
+        let start = self.index_or_error(outer)?;
+        let target = self.index_or_error(inner)?;
+        let order = self.acyclic_order(start)?;
+
+        // Longest number of steps from each bag down to the target, None if it can't be reached.
+        let mut longest: Vec<Option<usize>> = vec![None; self.colors.len()];
+        for index in order {
+            longest[index] = if index == target {
+                Some(0)
+            } else {
+                self.children[index]
+                    .iter()
+                    .filter_map(|(child, _)| longest[*child])
+                    .max()
+                    .map(|steps| steps + 1)
+            };
+        }
+
+        let mut steps = match longest[start] {
+            Some(steps) => steps,
+            None => return Ok(None),
+        };
+        let mut path = vec![self.colors[start].as_str()];
+        let mut index = start;
+        while steps > 0 {
+            index = self.children[index]
+                .iter()
+                .map(|(child, _)| *child)
+                .find(|child| longest[*child] == Some(steps - 1))
+                .unwrap();
+            path.push(self.colors[index].as_str());
+            steps -= 1;
+        }
+        Ok(Some(path))
+    }
+
+    /// Which bags belong to the subgraph.
+    fn select(&self, subgraph: &Subgraph) -> Result<Vec<bool>, BagError> {
+        let (color, edges) = match subgraph {
+            Subgraph::All => return Ok(vec![true; self.colors.len()]),
+            Subgraph::From(color) => (color, &self.children),
+            Subgraph::To(color) => (color, &self.parents),
+        };
+        let index = self.index_or_error(color)?;
+        let mut selected = self.reachable(index, edges);
+        selected[index] = true;
+        Ok(selected)
+    }
+
+    /// Graphviz DOT representation, one line per bag and one per rule edge labelled with the
+    /// quantity. The highlighted color, if any, is filled in gold.
+    pub fn to_dot(
+        &self,
+        subgraph: &Subgraph,
+        highlight: Option<&str>,
+    ) -> Result<Vec<String>, BagError> {
+        let selected = self.select(subgraph)?;
+        let mut lines = vec!["digraph bags {".to_string()];
+        for (index, color) in self.colors.iter().enumerate() {
+            if !selected[index] {
+                continue;
+            }
+            if highlight == Some(color.as_str()) {
+                lines.push(format!(
+                    "    {} [style=filled, fillcolor=gold];",
+                    dot_string(color)
+                ));
+            } else {
+                lines.push(format!("    {};", dot_string(color)));
+            }
+        }
+        for (outer, children) in self.children.iter().enumerate() {
+            for (inner, quantity) in children {
+                if selected[outer] && selected[*inner] {
+                    lines.push(format!(
+                        "    {} -> {} [label=\"{}\"];",
+                        dot_string(&self.colors[outer]),
+                        dot_string(&self.colors[*inner]),
+                        quantity
+                    ));
                 }
             }
-            contains.insert(bag_color.to_string(), false);
-            false
-        });
+        }
+        lines.push("}".to_string());
+        Ok(lines)
+    }
+
+    /// JSON adjacency list: an object with a member per bag listing the bags directly inside it
+    /// and their quantities.
+    pub fn to_json(&self, subgraph: &Subgraph) -> Result<Vec<String>, BagError> {
+        let selected = self.select(subgraph)?;
+        let members: Vec<String> = self
+            .children
+            .iter()
+            .enumerate()
+            .filter(|(outer, _)| selected[*outer])
+            .map(|(outer, children)| {
+                let inner_bags: Vec<String> = children
+                    .iter()
+                    .filter(|(inner, _)| selected[*inner])
+                    .map(|(inner, quantity)| {
+                        format!(
+                            "{{\"color\": {}, \"count\": {}}}",
+                            json_string(&self.colors[*inner]),
+                            quantity
+                        )
+                    })
+                    .collect();
+                format!(
+                    "  {}: [{}]",
+                    json_string(&self.colors[outer]),
+                    inner_bags.join(", ")
+                )
+            })
+            .collect();
+
+        let last = members.len().saturating_sub(1);
+        let mut lines = vec!["{".to_string()];
+        lines.extend(members.into_iter().enumerate().map(|(index, member)| {
+            if index == last {
+                member
+            } else {
+                member + ","
+            }
+        }));
+        lines.push("}".to_string());
+        Ok(lines)
     }
 
-    // Iterate over all rule colors.
-    let mut count = 0_usize;
-    for (bag_color, _) in rules.iter() {
-        if contains_shiny_gold(&rules, &mut cache, &bag_color) {
-            count += 1
+    /// Looks for cycles and references to colors without a rule in the whole rule set, and for
+    /// colors unrelated to the given one.
+    pub fn validate(&self, color: &str) -> Validation {
+        let all: Vec<usize> = (0..self.colors.len()).collect();
+        let (_, cycles) = self.depth_first(&all);
+
+        let dangling = all
+            .iter()
+            .flat_map(|outer| {
+                self.children[*outer]
+                    .iter()
+                    .filter(|(inner, _)| !self.has_rule[*inner])
+                    .map(move |(inner, _)| {
+                        (self.colors[*outer].clone(), self.colors[*inner].clone())
+                    })
+            })
+            .collect();
+
+        let mut unreachable: Vec<String> = match self.index(color) {
+            Some(index) => {
+                let ancestors = self.reachable(index, &self.parents);
+                let descendants = self.reachable(index, &self.children);
+                all.iter()
+                    .filter(|i| **i != index && !ancestors[**i] && !descendants[**i])
+                    .map(|i| self.colors[*i].clone())
+                    .collect()
+            }
+            None => self.colors.clone(),
+        };
+        unreachable.sort();
+
+        Validation {
+            cycles: cycles.iter().map(|cycle| self.names(cycle)).collect(),
+            dangling,
+            unreachable,
         }
     }
+}
 
-    count
+pub fn count_bags_containing_shiny_gold(lines: &[String]) -> usize {
+    BagGraph::parse(lines).ancestors("shiny gold").len()
 }
 
 // --- Part Two ---
@@ -138,29 +638,9 @@ pub fn count_bags_containing_shiny_gold(lines: &[String]) -> usize {
 //
 // How many individual bags are required inside your single shiny gold bag?
 
-pub fn count_bags_inside_shiny_gold(lines: &[String]) -> usize {
-    let rules = parse_bag_rules(lines);
-
-    // Cache of already checked bags & their capacity
-    let mut cache = HashMap::<String, usize>::new();
-
-    fn find_capacity(
-        rules: &HashMap<String, InnerBagsRules>,
-        cache: &mut HashMap<String, usize>,
-        bag_color: &str,
-    ) -> usize {
-        return cache.get(bag_color).copied().unwrap_or_else(|| {
-            let mut count = 0_usize;
-            for (inner_color, quantity) in rules.get(bag_color).unwrap().iter() {
-                // Add the (bag iself + the inner bags) * number of times
-                count += (1 + find_capacity(rules, cache, inner_color)) * quantity;
-            }
-            cache.insert(bag_color.to_string(), count);
-            count
-        });
-    }
-
-    find_capacity(&rules, &mut cache, &"shiny gold".to_string())
+/// Fails if the shiny gold bags can contain themselves, or contain bags without a rule.
+pub fn count_bags_inside_shiny_gold(lines: &[String]) -> Result<usize, BagError> {
+    BagGraph::parse(lines).total_contents("shiny gold")
 }
 
 #[cfg(test)]
@@ -254,4 +734,328 @@ mod tests {
             ("dotted black".to_string(), vec![])
         );
     }
+
+    fn example_graph() -> BagGraph {
+        let input_text = "light red bags contain 1 bright white bag, 2 muted yellow bags.
+dark orange bags contain 3 bright white bags, 4 muted yellow bags.
+bright white bags contain 1 shiny gold bag.
+muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.
+shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.
+dark olive bags contain 3 faded blue bags, 4 dotted black bags.
+vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.
+faded blue bags contain no other bags.
+dotted black bags contain no other bags.";
+        let input: Vec<String> = input_text.lines().map(|line| line.to_string()).collect();
+        BagGraph::parse(&input)
+    }
+
+    #[test]
+    pub fn test_bag_graph_edges() {
+        let graph = example_graph();
+        assert_eq!(graph.colors().len(), 9);
+        assert!(graph.contains_color("dotted black"));
+        assert!(!graph.contains_color("pale green"));
+        assert_eq!(
+            graph.children("muted yellow"),
+            vec![("shiny gold", 2), ("faded blue", 9)]
+        );
+        assert_eq!(graph.children("faded blue"), vec![]);
+        assert_eq!(
+            graph.parents("shiny gold"),
+            vec![("bright white", 1), ("muted yellow", 2)]
+        );
+        assert_eq!(graph.children("pale green"), vec![]);
+    }
+
+    #[test]
+    pub fn test_bag_graph_ancestors() {
+        let graph = example_graph();
+        assert_eq!(
+            graph.ancestors("shiny gold"),
+            vec!["bright white", "dark orange", "light red", "muted yellow"]
+        );
+        assert_eq!(
+            graph.ancestors("bright white"),
+            vec!["dark orange", "light red"]
+        );
+        assert!(graph.ancestors("light red").is_empty());
+        assert!(graph.ancestors("pale green").is_empty());
+    }
+
+    #[test]
+    pub fn test_bag_graph_total_contents() {
+        let graph = example_graph();
+        assert_eq!(graph.total_contents("shiny gold"), Ok(32));
+        assert_eq!(graph.total_contents("vibrant plum"), Ok(11));
+        assert_eq!(graph.total_contents("faded blue"), Ok(0));
+        assert_eq!(
+            graph.total_contents("pale green"),
+            Err(BagError::UnknownColor("pale green".to_string()))
+        );
+
+        let input_text = "shiny gold bags contain 2 dark red bags.
+dark red bags contain 2 dark orange bags.
+dark orange bags contain 2 dark yellow bags.
+dark yellow bags contain 2 dark green bags.
+dark green bags contain 2 dark blue bags.
+dark blue bags contain 2 dark violet bags.
+dark violet bags contain no other bags.";
+        let input: Vec<String> = input_text.lines().map(|line| line.to_string()).collect();
+        assert_eq!(count_bags_inside_shiny_gold(&input), Ok(126));
+
+        let input = vec![
+            "shiny gold bags contain 2 dark red bags.".to_string(),
+            "dark red bags contain 1 shiny gold bag.".to_string(),
+        ];
+        assert!(matches!(
+            count_bags_inside_shiny_gold(&input),
+            Err(BagError::Cycle(_))
+        ));
+        let input = vec!["shiny gold bags contain 2 dark red bags.".to_string()];
+        assert_eq!(
+            count_bags_inside_shiny_gold(&input),
+            Err(BagError::DanglingColor("dark red".to_string()))
+        );
+    }
+
+    #[test]
+    pub fn test_bag_graph_paths() {
+        let graph = example_graph();
+        assert_eq!(
+            graph.shortest_path("light red", "shiny gold"),
+            Ok(Some(vec!["light red", "bright white", "shiny gold"]))
+        );
+        assert_eq!(
+            graph.shortest_path("light red", "faded blue"),
+            Ok(Some(vec!["light red", "muted yellow", "faded blue"]))
+        );
+        assert_eq!(
+            graph.longest_path("light red", "faded blue"),
+            Ok(Some(vec![
+                "light red",
+                "bright white",
+                "shiny gold",
+                "dark olive",
+                "faded blue"
+            ]))
+        );
+        assert_eq!(
+            graph.longest_path("shiny gold", "shiny gold"),
+            Ok(Some(vec!["shiny gold"]))
+        );
+        assert_eq!(graph.shortest_path("shiny gold", "light red"), Ok(None));
+        assert_eq!(graph.longest_path("shiny gold", "light red"), Ok(None));
+        assert_eq!(
+            graph.shortest_path("shiny gold", "pale green"),
+            Err(BagError::UnknownColor("pale green".to_string()))
+        );
+    }
+
+    fn to_lines(input_text: &str) -> Vec<String> {
+        input_text.lines().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    pub fn test_valid_rules() {
+        let validation = example_graph().validate("shiny gold");
+        assert!(validation.is_valid());
+        assert_eq!(validation, Validation::default());
+
+        let validation = example_graph().validate("dark olive");
+        assert_eq!(validation.unreachable, vec!["vibrant plum".to_string()]);
+        assert!(validation.is_valid());
+    }
+
+    #[test]
+    pub fn test_cyclic_rules() {
+        let graph = BagGraph::parse(&to_lines(
+            "shiny gold bags contain 1 dark red bag, 2 dark blue bags.
+dark red bags contain 2 dark orange bags.
+dark orange bags contain 1 shiny gold bag.
+dark blue bags contain no other bags.
+light red bags contain 1 light red bag.",
+        ));
+        let cycle = vec![
+            "dark orange".to_string(),
+            "shiny gold".to_string(),
+            "dark red".to_string(),
+            "dark orange".to_string(),
+        ];
+        assert_eq!(
+            graph.total_contents("shiny gold"),
+            Err(BagError::Cycle(vec![
+                "shiny gold".to_string(),
+                "dark red".to_string(),
+                "dark orange".to_string(),
+                "shiny gold".to_string(),
+            ]))
+        );
+        assert_eq!(graph.total_contents("dark blue"), Ok(0));
+        assert!(matches!(
+            graph.longest_path("dark red", "dark blue"),
+            Err(BagError::Cycle(_))
+        ));
+        assert_eq!(
+            graph.shortest_path("dark red", "dark blue"),
+            Ok(Some(vec![
+                "dark red",
+                "dark orange",
+                "shiny gold",
+                "dark blue"
+            ]))
+        );
+        assert_eq!(
+            graph.ancestors("shiny gold"),
+            vec!["dark orange", "dark red", "shiny gold"]
+        );
+
+        let validation = graph.validate("shiny gold");
+        assert!(!validation.is_valid());
+        assert_eq!(
+            validation.cycles,
+            vec![
+                cycle,
+                vec!["light red".to_string(), "light red".to_string()]
+            ]
+        );
+        assert_eq!(validation.unreachable, vec!["light red".to_string()]);
+        assert_eq!(
+            BagError::Cycle(validation.cycles[1].clone()).to_string(),
+            "cycle light red -> light red"
+        );
+    }
+
+    #[test]
+    pub fn test_dangling_rules() {
+        let graph = BagGraph::parse(&to_lines(
+            "shiny gold bags contain 1 dark red bag, 2 dark blue bags.
+dark blue bags contain no other bags.",
+        ));
+        assert_eq!(
+            graph.total_contents("shiny gold"),
+            Err(BagError::DanglingColor("dark red".to_string()))
+        );
+        assert_eq!(graph.total_contents("dark blue"), Ok(0));
+        assert_eq!(
+            graph.validate("shiny gold").dangling,
+            vec![("shiny gold".to_string(), "dark red".to_string())]
+        );
+    }
+
+    #[test]
+    pub fn test_deep_rules() {
+        // Deep enough to overflow the stack with a recursive search.
+        let depth = 200_000;
+        let mut rules: HashMap<String, InnerBagsRules> = (0..depth)
+            .map(|level| {
+                (
+                    format!("bag {}", level),
+                    vec![(format!("bag {}", level + 1), 1)],
+                )
+            })
+            .collect();
+        rules.insert(format!("bag {}", depth), vec![]);
+        let graph = BagGraph::from_rules(&rules);
+        assert_eq!(graph.total_contents("bag 0"), Ok(depth));
+        assert_eq!(
+            graph
+                .longest_path("bag 0", &format!("bag {}", depth))
+                .map(|path| path.map(|path| path.len())),
+            Ok(Some(depth + 1))
+        );
+        assert!(graph.validate("bag 0").is_valid());
+    }
+
+    #[test]
+    pub fn test_dot_export() {
+        let graph = example_graph();
+        let dot = graph
+            .to_dot(
+                &Subgraph::From("shiny gold".to_string()),
+                Some("shiny gold"),
+            )
+            .unwrap();
+        assert_eq!(
+            dot,
+            vec![
+                "digraph bags {",
+                "    \"dark olive\";",
+                "    \"dotted black\";",
+                "    \"faded blue\";",
+                "    \"shiny gold\" [style=filled, fillcolor=gold];",
+                "    \"vibrant plum\";",
+                "    \"dark olive\" -> \"faded blue\" [label=\"3\"];",
+                "    \"dark olive\" -> \"dotted black\" [label=\"4\"];",
+                "    \"shiny gold\" -> \"dark olive\" [label=\"1\"];",
+                "    \"shiny gold\" -> \"vibrant plum\" [label=\"2\"];",
+                "    \"vibrant plum\" -> \"faded blue\" [label=\"5\"];",
+                "    \"vibrant plum\" -> \"dotted black\" [label=\"6\"];",
+                "}",
+            ]
+        );
+
+        let dot = graph.to_dot(&Subgraph::All, None).unwrap();
+        assert_eq!(dot.len(), 1 + 9 + 13 + 1);
+        assert_eq!(
+            graph.to_dot(&Subgraph::To("pale green".to_string()), None),
+            Err(BagError::UnknownColor("pale green".to_string()))
+        );
+    }
+
+    #[test]
+    pub fn test_json_export() {
+        let graph = example_graph();
+        assert_eq!(
+            graph
+                .to_json(&Subgraph::To("shiny gold".to_string()))
+                .unwrap(),
+            vec![
+                "{",
+                r#"  "bright white": [{"color": "shiny gold", "count": 1}],"#,
+                r#"  "dark orange": [{"color": "bright white", "count": 3}, {"color": "muted yellow", "count": 4}],"#,
+                r#"  "light red": [{"color": "bright white", "count": 1}, {"color": "muted yellow", "count": 2}],"#,
+                r#"  "muted yellow": [{"color": "shiny gold", "count": 2}],"#,
+                r#"  "shiny gold": []"#,
+                "}",
+            ]
+        );
+        assert_eq!(
+            graph
+                .to_json(&Subgraph::From("faded blue".to_string()))
+                .unwrap(),
+            vec!["{", r#"  "faded blue": []"#, "}"]
+        );
+    }
+
+    #[test]
+    pub fn test_overflowing_contents() {
+        // Every level doubles the number of bags, 2^71 - 2 bags inside bag 0.
+        let depth = 70;
+        let mut rules: HashMap<String, InnerBagsRules> = (0..depth)
+            .map(|level| {
+                (
+                    format!("bag {}", level),
+                    vec![(format!("bag {}", level + 1), 2)],
+                )
+            })
+            .collect();
+        rules.insert(format!("bag {}", depth), vec![]);
+        let graph = BagGraph::from_rules(&rules);
+
+        assert_eq!(
+            graph.total_contents("bag 0"),
+            Err(BagError::Overflow("bag 0".to_string()))
+        );
+        assert_eq!(
+            graph
+                .total_contents_big("bag 0")
+                .map(|bags| bags.to_string()),
+            Ok("2361183241434822606846".to_string())
+        );
+        assert_eq!(graph.total_contents("bag 10"), Ok((1 << 61) - 2));
+        assert_eq!(
+            graph.total_contents_big("bag 10"),
+            Ok(BigUint::from((1_u64 << 61) - 2))
+        );
+    }
 }
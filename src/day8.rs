@@ -55,78 +55,18 @@
 //
 // Run your copy of the boot code. Immediately before any instruction is executed a second time,
 // what value is in the accumulator?
-use lazy_static::lazy_static;
-use regex::{Captures, Regex};
-use std::collections::HashSet;
-
-#[derive(PartialEq, Debug, Clone)]
-enum Instruction {
-    Nop(i32),
-    Acc(i32),
-    Jmp(i32),
-}
-
-fn parse_instruction(line: &str) -> Instruction {
-    //let REGEX = Regex::new(r"^(nop|acc|jmp) ([+\-]\d+)$").unwrap();
-    lazy_static! {
-        static ref REGEX: Regex = Regex::new(r"^(nop|acc|jmp) ([+\-]\d+)$").unwrap();
-    }
-    let cap: Captures = REGEX
-        .captures(line)
-        .unwrap_or_else(|| panic!("Unexpected line: {}", line));
-    let operation = cap.get(1).unwrap().as_str();
-    let argument = cap.get(2).unwrap().as_str().parse::<i32>().unwrap();
-    match operation {
-        "nop" => Instruction::Nop(argument),
-        "acc" => Instruction::Acc(argument),
-        "jmp" => Instruction::Jmp(argument),
-        _ => panic!("Unexpected instruction: {}", line),
-    }
-}
+use crate::handheld::analysis::{switched, ControlFlowGraph};
+use crate::handheld::{ExecutionResult, Instruction, InstructionSet, Machine};
 
 fn parse_instructions(lines: &[String]) -> Vec<Instruction> {
-    lines.iter().map(|line| parse_instruction(line)).collect()
-}
-
-fn run_instruction(instruction: &Instruction, accumulator: &i32, position: &usize) -> (i32, usize) {
-    match instruction {
-        Instruction::Nop(_) => (*accumulator, position + 1),
-        Instruction::Acc(acc) => (*accumulator + *acc, position + 1),
-        Instruction::Jmp(pos) => (*accumulator, ((*position as i32) + pos) as usize),
-    }
-}
-
-/// Executes the given instructions set. If it finds a loop it returns an error.
-fn run_program(
-    instructions: &[Instruction],
-    accumulator: &i32,
-    position: &usize,
-    accessed_instructions: Option<&HashSet<usize>>,
-) -> Result<i32, (i32, usize)> {
-    let mut accumulator = *accumulator;
-    let mut position = *position;
-    let mut accessed_instructions = match accessed_instructions {
-        Some(accessed_instructions) => accessed_instructions.clone(),
-        None => HashSet::new(),
-    };
-    while position < instructions.len() {
-        let (next_accumulator, next_position) =
-            run_instruction(&instructions[position], &accumulator, &position);
-        if accessed_instructions.contains(&next_position) {
-            return Err((accumulator, position));
-        }
-        accessed_instructions.insert(position);
-        accumulator = next_accumulator;
-        position = next_position;
-    }
-    Ok(accumulator)
+    InstructionSet::boot_code().parse_program(lines)
 }
 
 pub fn accumulator_value_before_entering_loop(lines: &[String]) -> i32 {
-    let instructions = parse_instructions(lines);
-    match run_program(&instructions, &0, &0, None) {
-        Ok(_) => panic!("Expected an infinite loop"),
-        Err(err) => err.0,
+    let mut machine = Machine::new(parse_instructions(lines));
+    match machine.run() {
+        ExecutionResult::InfiniteLoop(registers) => registers.accumulator as i32,
+        result => panic!("Expected an infinite loop, got {:?}", result),
     }
 }
 
@@ -174,84 +114,78 @@ pub fn accumulator_value_before_entering_loop(lines: &[String]) -> i32 {
 // jmp). What is the value of the accumulator after the program terminates?
 
 pub fn accumulator_value_fixing_loop(lines: &[String]) -> i32 {
-    let instructions = parse_instructions(lines);
-    for position in 0..instructions.len() {
-        let mut modified_instructions = instructions.clone();
-        match instructions[position] {
-            Instruction::Nop(argument) => {
-                modified_instructions[position] = Instruction::Jmp(argument)
-            }
-            Instruction::Acc(_) => continue,
-            Instruction::Jmp(argument) => {
-                modified_instructions[position] = Instruction::Nop(argument)
-            }
-        }
-        if let Ok(acc) = run_program(&modified_instructions, &0, &0, None) {
-            return acc;
-        }
+    let mut instructions = parse_instructions(lines);
+    let patches = ControlFlowGraph::new(&instructions).terminating_patches();
+    let patch = patches
+        .first()
+        .unwrap_or_else(|| panic!("Didn't found any permutation that solves the loop"));
+    patch.apply(&mut instructions);
+    match Machine::new(instructions).run() {
+        ExecutionResult::Terminated(registers) => registers.accumulator as i32,
+        result => panic!(
+            "Expected the patched program to terminate, got {:?}",
+            result
+        ),
     }
-    panic!("Didn't found any permutation that solves the loop");
 }
 
 pub fn accumulator_value_fixing_loop_fast(lines: &[String]) -> i32 {
-    let instructions = parse_instructions(lines);
-    let mut accumulator = 0;
-    let mut position = 0;
-    let mut accessed_instructions = HashSet::<usize>::new();
-    while position < instructions.len() {
-        // Check if the instruction can be swapped.
-        let switched_op: Option<Instruction> = match instructions[position] {
-            Instruction::Nop(argument) => Some(Instruction::Jmp(argument)),
-            Instruction::Acc(_) => None,
-            Instruction::Jmp(argument) => Some(Instruction::Nop(argument)),
-        };
-        // If there is the option to swap, run a single step with switched instruction,
-        // then run the program from there.
-        if let Some(instruction) = switched_op {
-            let (next_accumulator, next_position) =
-                run_instruction(&instruction, &accumulator, &position);
-            if !accessed_instructions.contains(&next_position) {
-                // TODO: Loop cache?
-                if let Ok(acc) = run_program(
-                    &instructions,
-                    &next_accumulator,
-                    &next_position,
-                    Some(&accessed_instructions),
-                ) {
-                    return acc;
-                }
+    let mut machine = Machine::new(parse_instructions(lines));
+    while let Some(instruction) = machine.current_instruction() {
+        // If there is the option to swap, run from here with the switched instruction. Instructions
+        // already executed stay marked, so going back to them is a loop.
+        if let Some(instruction) = switched(&instruction) {
+            let mut patched = machine.clone();
+            patched.patch(machine.registers().pc as usize, instruction);
+            if let ExecutionResult::Terminated(registers) = patched.run() {
+                return registers.accumulator as i32;
             }
         }
         // Move 1 step further
-        let (next_accumulator, next_position) =
-            run_instruction(&instructions[position], &accumulator, &position);
-        if accessed_instructions.contains(&next_position) {
-            panic!("Both the permutation & the original instruction lead to loop");
+        if let Some(result) = machine.step() {
+            panic!(
+                "Both the permutation & the original instruction stop with {:?}",
+                result
+            );
         }
-        accessed_instructions.insert(position);
-        accumulator = next_accumulator;
-        position = next_position;
     }
-    accumulator
+    machine.registers().accumulator as i32
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::handheld::{ACC, JMP, NOP};
+
+    fn example_program() -> Vec<String> {
+        let input_text = "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6";
+        input_text.lines().map(|line| line.to_string()).collect()
+    }
 
     #[test]
     pub fn test_parse_instruction() {
+        let parse_instruction = |line: &str| InstructionSet::boot_code().parse_instruction(line);
         assert_eq!(
             parse_instruction(&String::from("nop +0")),
-            Instruction::Nop(0)
+            Instruction::new(NOP, 0)
         );
         assert_eq!(
             parse_instruction(&String::from("acc -117")),
-            Instruction::Acc(-117)
+            Instruction::new(ACC, -117)
         );
         assert_eq!(
             parse_instruction(&String::from("jmp +99")),
-            Instruction::Jmp(99)
+            Instruction::new(JMP, 99)
+        );
+    }
+
+    #[test]
+    pub fn test_example() {
+        assert_eq!(
+            accumulator_value_before_entering_loop(&example_program()),
+            5
         );
+        assert_eq!(accumulator_value_fixing_loop(&example_program()), 8);
+        assert_eq!(accumulator_value_fixing_loop_fast(&example_program()), 8);
     }
 }
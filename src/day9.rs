@@ -45,27 +45,95 @@
 //
 // The first step of attacking the weakness in the XMAS data is to find the first number in the list (after the preamble) which is not the sum of two of the 25 numbers before it. What is the first number that does not have this property?
 
-pub fn find_first_invalid(lines: &[String], preamble: usize) -> i64 {
-    let numbers: Vec<i64> = lines
-        .iter()
-        .map(|line| line.parse::<i64>().unwrap())
-        .collect();
-    for i in (preamble)..numbers.len() {
-        let target = numbers[i];
-        let mut found = false;
-        'search: for a in (i - preamble)..i {
-            for b in (a + 1)..i {
-                if (numbers[a] + numbers[b]) == target {
-                    found = true;
-                    break 'search;
+use std::collections::{HashMap, VecDeque};
+
+/// A number that is not the sum of two different numbers of the window before it.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct InvalidNumber {
+    pub index: usize,
+    pub value: i64,
+}
+
+/// Validates numbers one by one, keeping the sums of every pair of the window so each number is
+/// checked with a single lookup. Adding a number updates the sums in O(preamble).
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct XmasValidator {
+    preamble: usize,
+    window: VecDeque<i64>,
+    pair_sums: HashMap<i64, usize>,
+    index: usize,
+}
+
+impl XmasValidator {
+    pub fn new(preamble: usize) -> XmasValidator {
+        XmasValidator {
+            preamble,
+            window: VecDeque::with_capacity(preamble + 1),
+            pair_sums: HashMap::new(),
+            index: 0,
+        }
+    }
+
+    /// Whether the number is the sum of two numbers with different values in the window.
+    pub fn is_valid(&self, number: i64) -> bool {
+        self.pair_sums.get(&number).is_some_and(|count| *count > 0)
+    }
+
+    /// Consumes the next number, returning it if it is invalid. Numbers in the preamble are always
+    /// valid. Invalid numbers still enter the window, as they are part of the transmission.
+    pub fn push(&mut self, number: i64) -> Option<InvalidNumber> {
+        let invalid = if self.index >= self.preamble && !self.is_valid(number) {
+            Some(InvalidNumber {
+                index: self.index,
+                value: number,
+            })
+        } else {
+            None
+        };
+        if self.window.len() == self.preamble {
+            if let Some(oldest) = self.window.pop_front() {
+                for other in self.window.iter().filter(|other| **other != oldest) {
+                    let count = self.pair_sums.get_mut(&(oldest + other)).unwrap();
+                    *count -= 1;
+                    if *count == 0 {
+                        self.pair_sums.remove(&(oldest + other));
+                    }
                 }
             }
         }
-        if !found {
-            return target;
+        if self.preamble > 0 {
+            for other in self.window.iter().filter(|other| **other != number) {
+                *self.pair_sums.entry(number + other).or_insert(0) += 1;
+            }
+            self.window.push_back(number);
         }
+        self.index += 1;
+        invalid
     }
-    panic!("Couldn't find any invalid number");
+}
+
+/// Lazily validates a stream of numbers, yielding the invalid ones.
+pub fn invalid_numbers<I: IntoIterator<Item = i64>>(
+    numbers: I,
+    preamble: usize,
+) -> impl Iterator<Item = InvalidNumber> {
+    let mut validator = XmasValidator::new(preamble);
+    numbers
+        .into_iter()
+        .filter_map(move |number| validator.push(number))
+}
+
+fn parse_numbers(lines: &[String]) -> impl Iterator<Item = i64> + '_ {
+    lines.iter().map(|line| {
+        line.parse::<i64>()
+            .unwrap_or_else(|_| panic!("Unexpected number {}", line))
+    })
+}
+
+pub fn find_first_invalid(lines: &[String], preamble: usize) -> Option<i64> {
+    invalid_numbers(parse_numbers(lines), preamble)
+        .next()
+        .map(|invalid| invalid.value)
 }
 
 // --- Part Two ---
@@ -103,30 +171,104 @@ pub fn find_first_invalid(lines: &[String], preamble: usize) -> i64 {
 //
 // What is the encryption weakness in your XMAS-encrypted list of numbers?
 
-pub fn find_vulnerability(lines: &[String], preamble: usize) -> i64 {
-    let numbers: Vec<i64> = lines
-        .iter()
-        .map(|line| line.parse::<i64>().unwrap())
-        .collect();
-    let target = find_first_invalid(lines, preamble);
-    for i in 0..numbers.len() {
-        let mut acc = 0_i64;
-        for j in i..numbers.len() {
-            acc += numbers[j];
-            match acc {
-                acc if acc == target => {
-                    let set = &numbers[i..=j];
-                    let min = set.iter().min().unwrap();
-                    let max = set.iter().max().unwrap();
-                    return min + max;
-                }
-                acc if acc > target => break,
-                _ => continue,
+use std::ops::RangeInclusive;
+
+/// Sets need at least two numbers, a number alone is not a sum.
+const MIN_RANGE_LEN: usize = 2;
+
+/// How to search for contiguous ranges.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum RangeMode {
+    /// Sliding window growing on the right and shrinking on the left, with no extra memory. Only
+    /// valid when no number is negative.
+    TwoPointers,
+    /// Looks up the prefix sums seen so far in a hash map. Valid for any numbers.
+    PrefixSums,
+}
+
+/// First contiguous range of at least two numbers summing to the target: the one ending first and,
+/// among those, the longest. Both modes find the same range.
+pub fn find_range(numbers: &[i64], target: i64, mode: RangeMode) -> Option<RangeInclusive<usize>> {
+    match mode {
+        RangeMode::TwoPointers => find_range_two_pointers(numbers, target),
+        RangeMode::PrefixSums => find_range_prefix_sums(numbers, target),
+    }
+}
+
+fn find_range_two_pointers(numbers: &[i64], target: i64) -> Option<RangeInclusive<usize>> {
+    let mut start = 0;
+    let mut sum = 0;
+    for (end, number) in numbers.iter().enumerate() {
+        if *number < 0 {
+            panic!("Unexpected negative number {} at {}", number, end);
+        }
+        sum += number;
+        // Leftmost start with a sum not above the target, so the longest candidate range.
+        while sum > target && start < end {
+            sum -= numbers[start];
+            start += 1;
+        }
+        if sum == target && end + 1 - start >= MIN_RANGE_LEN {
+            return Some(start..=end);
+        }
+    }
+    None
+}
+
+fn find_range_prefix_sums(numbers: &[i64], target: i64) -> Option<RangeInclusive<usize>> {
+    // First position where each prefix sum appears: sum of numbers[..position].
+    let mut first_positions: HashMap<i64, usize> = HashMap::new();
+    first_positions.insert(0, 0);
+    let mut sum = 0;
+    for (end, number) in numbers.iter().enumerate() {
+        sum += number;
+        if let Some(start) = first_positions.get(&(sum - target)) {
+            if end + 1 - start >= MIN_RANGE_LEN {
+                return Some(*start..=end);
             }
         }
+        first_positions.entry(sum).or_insert(end + 1);
+    }
+    None
+}
+
+/// Every contiguous range of at least two numbers summing to the target, sorted by end and then by
+/// start. Valid for any numbers.
+pub fn find_all_ranges(numbers: &[i64], target: i64) -> Vec<RangeInclusive<usize>> {
+    let mut positions: HashMap<i64, Vec<usize>> = HashMap::new();
+    positions.insert(0, vec![0]);
+    let mut ranges = Vec::new();
+    let mut sum = 0;
+    for (end, number) in numbers.iter().enumerate() {
+        sum += number;
+        if let Some(starts) = positions.get(&(sum - target)) {
+            ranges.extend(
+                starts
+                    .iter()
+                    .filter(|start| end + 1 - **start >= MIN_RANGE_LEN)
+                    .map(|start| *start..=end),
+            );
+        }
+        positions.entry(sum).or_default().push(end + 1);
     }
+    ranges
+}
+
+/// Sum of the smallest and largest numbers of the range.
+pub fn encryption_weakness(numbers: &[i64], range: RangeInclusive<usize>) -> i64 {
+    let set = &numbers[range];
+    set.iter().min().unwrap() + set.iter().max().unwrap()
+}
 
-    panic!("Couldn't find an exploit");
+pub fn find_vulnerability(lines: &[String], preamble: usize) -> i64 {
+    let numbers: Vec<i64> = parse_numbers(lines).collect();
+    let target = invalid_numbers(numbers.iter().copied(), preamble)
+        .next()
+        .expect("Couldn't find any invalid number")
+        .value;
+    let range =
+        find_range(&numbers, target, RangeMode::TwoPointers).expect("Couldn't find an exploit");
+    encryption_weakness(&numbers, range)
 }
 
 #[cfg(test)]
@@ -137,7 +279,47 @@ mod tests {
     pub fn test_find_first_invalid() {
         let input_text = "35\n20\n15\n25\n47\n40\n62\n55\n65\n95\n102\n117\n150\n182\n127\n219\n299\n277\n309\n576\n";
         let input: Vec<String> = input_text.lines().map(|line| line.to_string()).collect();
-        assert_eq!(find_first_invalid(&input, 5), 127);
+        assert_eq!(find_first_invalid(&input, 5), Some(127));
+        assert_eq!(find_first_invalid(&input[..14], 5), None);
+    }
+
+    #[test]
+    pub fn test_validator() {
+        let mut validator = XmasValidator::new(25);
+        for number in (20..=20).chain(1..=19).chain(21..=25) {
+            assert_eq!(validator.push(number), None);
+        }
+        assert!(validator.is_valid(26));
+        assert!(validator.is_valid(49));
+        assert!(!validator.is_valid(100));
+        assert!(!validator.is_valid(50));
+        assert_eq!(validator.push(45), None);
+        // 20 left the window.
+        assert!(validator.is_valid(26));
+        assert!(!validator.is_valid(65));
+        assert!(validator.is_valid(64));
+        assert!(validator.is_valid(66));
+        assert_eq!(
+            validator.push(65),
+            Some(InvalidNumber {
+                index: 26,
+                value: 65
+            })
+        );
+    }
+
+    #[test]
+    pub fn test_invalid_numbers() {
+        // Pairs must have different values, so 6 is not the sum of 3 and 3.
+        let numbers = vec![1, 2, 3, 3, 6, 9, 15, 4, 19];
+        assert_eq!(
+            invalid_numbers(numbers, 2).collect::<Vec<InvalidNumber>>(),
+            vec![
+                InvalidNumber { index: 3, value: 3 },
+                InvalidNumber { index: 4, value: 6 },
+                InvalidNumber { index: 7, value: 4 },
+            ]
+        );
     }
 
     #[test]
@@ -146,4 +328,51 @@ mod tests {
         let input: Vec<String> = input_text.lines().map(|line| line.to_string()).collect();
         assert_eq!(find_vulnerability(&input, 5), 62);
     }
+
+    #[test]
+    pub fn test_find_range() {
+        let numbers = vec![
+            35, 20, 15, 25, 47, 40, 62, 55, 65, 95, 102, 117, 150, 182, 127,
+        ];
+        for mode in [RangeMode::TwoPointers, RangeMode::PrefixSums].iter() {
+            assert_eq!(find_range(&numbers, 127, *mode), Some(2..=5));
+            assert_eq!(find_range(&numbers, 55, *mode), Some(0..=1));
+            assert_eq!(find_range(&numbers, 35, *mode), Some(1..=2));
+            assert_eq!(find_range(&numbers, 36, *mode), None);
+            assert_eq!(find_range(&numbers, 1000, *mode), None);
+        }
+        assert_eq!(encryption_weakness(&numbers, 2..=5), 62);
+
+        // Zeros make several ranges end at the same place, both modes pick the longest.
+        let numbers = vec![5, 0, 3, 0, 0, 4];
+        for mode in [RangeMode::TwoPointers, RangeMode::PrefixSums].iter() {
+            assert_eq!(find_range(&numbers, 3, *mode), Some(1..=2));
+            assert_eq!(find_range(&numbers, 5, *mode), Some(0..=1));
+        }
+    }
+
+    #[test]
+    pub fn test_find_range_negative_numbers() {
+        let numbers = vec![4, -2, 7, 1, -6, 5, 3];
+        assert_eq!(find_range(&numbers, 2, RangeMode::PrefixSums), Some(0..=1));
+        assert_eq!(find_range(&numbers, 10, RangeMode::PrefixSums), Some(0..=3));
+        assert_eq!(find_range(&numbers, 0, RangeMode::PrefixSums), Some(1..=4));
+        assert_eq!(find_range(&numbers, 100, RangeMode::PrefixSums), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unexpected negative number -2 at 1")]
+    pub fn test_two_pointers_rejects_negative_numbers() {
+        find_range(&[4, -2, 7], 2, RangeMode::TwoPointers);
+    }
+
+    #[test]
+    pub fn test_find_all_ranges() {
+        let numbers = vec![1, 2, 3, 0, 3, -3, 6];
+        assert_eq!(
+            find_all_ranges(&numbers, 3),
+            vec![0..=1, 2..=3, 3..=4, 2..=5, 5..=6]
+        );
+        assert_eq!(find_all_ranges(&numbers, 100), vec![]);
+    }
 }
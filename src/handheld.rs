@@ -0,0 +1,457 @@
+// Virtual machine running the boot code of the handheld game console from day 8.
+//
+// A program is a list of instructions, each one an opcode and a signed argument. The machine has
+// an accumulator and a program counter (pc). The instruction set is open: besides the boot code
+// opcodes (nop, acc and jmp) new opcodes can be added, each one a function updating the registers.
+
+pub mod analysis;
+pub mod assembler;
+pub mod trace;
+
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use std::fmt;
+
+/// Registers of the machine. The program counter is signed, so a jump before the first
+/// instruction is reported instead of wrapping around.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct Registers {
+    pub accumulator: i64,
+    pub pc: i64,
+}
+
+/// An operation of the instruction set. Executing it gives the new registers, including the program
+/// counter moved to the next instruction to run, or None if a register would overflow.
+#[derive(Clone, Copy)]
+pub struct Opcode {
+    pub name: &'static str,
+    pub execute: fn(Registers, i64) -> Option<Registers>,
+}
+
+/// Opcodes are identified by their name.
+impl PartialEq for Opcode {
+    fn eq(&self, other: &Opcode) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for Opcode {}
+
+impl fmt::Debug for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Opcode({})", self.name)
+    }
+}
+
+/// No operation, the argument is ignored.
+pub const NOP: Opcode = Opcode {
+    name: "nop",
+    execute: |registers, _| {
+        Some(Registers {
+            pc: registers.pc.checked_add(1)?,
+            ..registers
+        })
+    },
+};
+
+/// Adds the argument to the accumulator.
+pub const ACC: Opcode = Opcode {
+    name: "acc",
+    execute: |registers, argument| {
+        Some(Registers {
+            accumulator: registers.accumulator.checked_add(argument)?,
+            pc: registers.pc.checked_add(1)?,
+        })
+    },
+};
+
+/// Jumps relative to itself.
+pub const JMP: Opcode = Opcode {
+    name: "jmp",
+    execute: |registers, argument| {
+        Some(Registers {
+            pc: registers.pc.checked_add(argument)?,
+            ..registers
+        })
+    },
+};
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub argument: i64,
+}
+
+impl Instruction {
+    pub fn new(opcode: Opcode, argument: i64) -> Instruction {
+        Instruction { opcode, argument }
+    }
+}
+
+/// Same format the instructions are parsed from, e.g. `jmp -3`.
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {:+}", self.opcode.name, self.argument)
+    }
+}
+
+/// Opcodes a program can use.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct InstructionSet {
+    opcodes: Vec<Opcode>,
+}
+
+impl InstructionSet {
+    /// nop, acc and jmp.
+    pub fn boot_code() -> InstructionSet {
+        InstructionSet {
+            opcodes: vec![NOP, ACC, JMP],
+        }
+    }
+
+    /// Adds an opcode, replacing any opcode with the same name.
+    pub fn with(mut self, opcode: Opcode) -> InstructionSet {
+        self.opcodes.retain(|existing| existing.name != opcode.name);
+        self.opcodes.push(opcode);
+        self
+    }
+
+    pub fn opcode(&self, name: &str) -> Option<Opcode> {
+        self.opcodes
+            .iter()
+            .find(|opcode| opcode.name == name)
+            .copied()
+    }
+
+    /// Parses an instruction like `acc -99`.
+    pub fn parse_instruction(&self, line: &str) -> Instruction {
+        lazy_static! {
+            static ref REGEX: Regex = Regex::new(r"^([a-z]+) ([+\-]\d+)$").unwrap();
+        }
+        let cap: Captures = REGEX
+            .captures(line)
+            .unwrap_or_else(|| panic!("Unexpected line: {}", line));
+        let opcode = self
+            .opcode(&cap[1])
+            .unwrap_or_else(|| panic!("Unexpected instruction: {}", line));
+        let argument = cap[2]
+            .parse::<i64>()
+            .unwrap_or_else(|_| panic!("Unexpected argument: {}", line));
+        Instruction::new(opcode, argument)
+    }
+
+    pub fn parse_program(&self, lines: &[String]) -> Vec<Instruction> {
+        lines
+            .iter()
+            .map(|line| self.parse_instruction(line))
+            .collect()
+    }
+}
+
+/// Why the machine stopped, with the registers at that moment.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ExecutionResult {
+    /// The program counter points right after the last instruction.
+    Terminated(Registers),
+    /// The next instruction was already executed, so the program would run forever. The
+    /// registers are the ones right before running it a second time.
+    InfiniteLoop(Registers),
+    /// The program counter points before the first instruction or past the end of the program.
+    OutOfBounds(Registers),
+    /// The step limit was reached before the program stopped.
+    StepLimit(Registers),
+    /// Executing the next instruction would overflow a register. The registers are the ones right
+    /// before running it.
+    Overflow(Registers),
+}
+
+impl ExecutionResult {
+    pub fn registers(&self) -> Registers {
+        match self {
+            ExecutionResult::Terminated(registers)
+            | ExecutionResult::InfiniteLoop(registers)
+            | ExecutionResult::OutOfBounds(registers)
+            | ExecutionResult::StepLimit(registers)
+            | ExecutionResult::Overflow(registers) => *registers,
+        }
+    }
+}
+
+/// A program loaded in the machine, and the state of its execution.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Machine {
+    program: Vec<Instruction>,
+    registers: Registers,
+    executed: Vec<bool>,
+    steps: usize,
+    step_limit: Option<usize>,
+    overflow: bool,
+}
+
+impl Machine {
+    pub fn new(program: Vec<Instruction>) -> Machine {
+        let executed = vec![false; program.len()];
+        Machine {
+            program,
+            registers: Registers::default(),
+            executed,
+            steps: 0,
+            step_limit: None,
+            overflow: false,
+        }
+    }
+
+    /// Stops the machine after executing the given number of instructions.
+    pub fn with_step_limit(mut self, step_limit: usize) -> Machine {
+        self.step_limit = Some(step_limit);
+        self
+    }
+
+    pub fn program(&self) -> &[Instruction] {
+        &self.program
+    }
+
+    pub fn registers(&self) -> Registers {
+        self.registers
+    }
+
+    /// Number of instructions executed so far.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Whether the instruction at the given position was executed at least once.
+    pub fn was_executed(&self, position: usize) -> bool {
+        self.executed.get(position).copied().unwrap_or(false)
+    }
+
+    /// Replaces an instruction of the loaded program, keeping the execution state.
+    pub fn patch(&mut self, position: usize, instruction: Instruction) {
+        self.program[position] = instruction;
+    }
+
+    /// Back to the initial state, keeping the program and the step limit.
+    pub fn reset(&mut self) {
+        self.registers = Registers::default();
+        self.executed = vec![false; self.program.len()];
+        self.steps = 0;
+        self.overflow = false;
+    }
+
+    /// The instruction the program counter points to, if any.
+    pub fn current_instruction(&self) -> Option<Instruction> {
+        if self.registers.pc < 0 {
+            return None;
+        }
+        self.program.get(self.registers.pc as usize).copied()
+    }
+
+    /// Why the machine can't execute the next instruction, or None if it can.
+    pub fn halted(&self) -> Option<ExecutionResult> {
+        let registers = self.registers;
+        let pc = registers.pc;
+        if self.overflow {
+            Some(ExecutionResult::Overflow(registers))
+        } else if pc == self.program.len() as i64 {
+            Some(ExecutionResult::Terminated(registers))
+        } else if pc < 0 || pc > self.program.len() as i64 {
+            Some(ExecutionResult::OutOfBounds(registers))
+        } else if self.executed[pc as usize] {
+            Some(ExecutionResult::InfiniteLoop(registers))
+        } else if self.step_limit.is_some_and(|limit| self.steps >= limit) {
+            Some(ExecutionResult::StepLimit(registers))
+        } else {
+            None
+        }
+    }
+
+    /// Executes a single instruction. If the machine is halted nothing is executed and the reason
+    /// is returned instead.
+    pub fn step(&mut self) -> Option<ExecutionResult> {
+        if let Some(result) = self.halted() {
+            return Some(result);
+        }
+        let position = self.registers.pc as usize;
+        let instruction = self.program[position];
+        match (instruction.opcode.execute)(self.registers, instruction.argument) {
+            Some(registers) => self.registers = registers,
+            None => {
+                self.overflow = true;
+                return self.halted();
+            }
+        }
+        self.executed[position] = true;
+        self.steps += 1;
+        None
+    }
+
+    /// Executes instructions until the machine halts.
+    pub fn run(&mut self) -> ExecutionResult {
+        loop {
+            if let Some(result) = self.step() {
+                return result;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_program() -> Vec<Instruction> {
+        let input_text = "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6";
+        let lines: Vec<String> = input_text.lines().map(|line| line.to_string()).collect();
+        InstructionSet::boot_code().parse_program(&lines)
+    }
+
+    #[test]
+    pub fn test_parse_instruction() {
+        let instruction_set = InstructionSet::boot_code();
+        assert_eq!(
+            instruction_set.parse_instruction("acc -117"),
+            Instruction::new(ACC, -117)
+        );
+        assert_eq!(
+            instruction_set.parse_instruction("jmp +99").to_string(),
+            "jmp +99"
+        );
+        assert_eq!(instruction_set.opcode("mul"), None);
+    }
+
+    #[test]
+    pub fn test_run_until_loop() {
+        let mut machine = Machine::new(example_program());
+        assert_eq!(
+            machine.run(),
+            ExecutionResult::InfiniteLoop(Registers {
+                accumulator: 5,
+                pc: 1
+            })
+        );
+        assert_eq!(machine.steps(), 7);
+        assert!(machine.was_executed(7));
+        assert!(!machine.was_executed(5));
+    }
+
+    #[test]
+    pub fn test_step_and_patch() {
+        let mut machine = Machine::new(example_program());
+        assert_eq!(machine.step(), None);
+        assert_eq!(machine.step(), None);
+        assert_eq!(
+            machine.registers(),
+            Registers {
+                accumulator: 1,
+                pc: 2
+            }
+        );
+        assert_eq!(
+            machine.current_instruction(),
+            Some(Instruction::new(JMP, 4))
+        );
+
+        machine.patch(7, Instruction::new(NOP, -4));
+        assert_eq!(
+            machine.run(),
+            ExecutionResult::Terminated(Registers {
+                accumulator: 8,
+                pc: 9
+            })
+        );
+        assert_eq!(machine.step().map(|result| result.registers().pc), Some(9));
+
+        machine.reset();
+        assert_eq!(machine.registers(), Registers::default());
+        assert_eq!(machine.run().registers().accumulator, 8);
+    }
+
+    #[test]
+    pub fn test_out_of_bounds() {
+        let program = vec![Instruction::new(ACC, 3), Instruction::new(JMP, -2)];
+        assert_eq!(
+            Machine::new(program).run(),
+            ExecutionResult::OutOfBounds(Registers {
+                accumulator: 3,
+                pc: -1
+            })
+        );
+        let program = vec![Instruction::new(JMP, 3), Instruction::new(NOP, 0)];
+        assert_eq!(
+            Machine::new(program).run(),
+            ExecutionResult::OutOfBounds(Registers {
+                accumulator: 0,
+                pc: 3
+            })
+        );
+    }
+
+    #[test]
+    pub fn test_step_limit() {
+        let mut machine = Machine::new(example_program()).with_step_limit(3);
+        assert_eq!(
+            machine.run(),
+            ExecutionResult::StepLimit(Registers {
+                accumulator: 1,
+                pc: 6
+            })
+        );
+        assert_eq!(machine.steps(), 3);
+    }
+
+    #[test]
+    pub fn test_custom_opcode() {
+        // Multiplies the accumulator by the argument.
+        const MUL: Opcode = Opcode {
+            name: "mul",
+            execute: |registers, argument| {
+                Some(Registers {
+                    accumulator: registers.accumulator.checked_mul(argument)?,
+                    pc: registers.pc.checked_add(1)?,
+                })
+            },
+        };
+        let instruction_set = InstructionSet::boot_code().with(MUL);
+        let lines: Vec<String> = vec!["acc +3".to_string(), "mul -4".to_string()];
+        let program = instruction_set.parse_program(&lines);
+        assert_eq!(program[1], Instruction::new(MUL, -4));
+        assert_eq!(
+            Machine::new(program).run(),
+            ExecutionResult::Terminated(Registers {
+                accumulator: -12,
+                pc: 2
+            })
+        );
+    }
+
+    #[test]
+    pub fn test_overflow() {
+        let program = vec![
+            Instruction::new(ACC, i64::MAX),
+            Instruction::new(ACC, 1),
+            Instruction::new(NOP, 0),
+        ];
+        let mut machine = Machine::new(program);
+        let overflow = ExecutionResult::Overflow(Registers {
+            accumulator: i64::MAX,
+            pc: 1,
+        });
+        assert_eq!(machine.run(), overflow);
+        assert_eq!(machine.steps(), 1);
+        assert!(!machine.was_executed(1));
+        assert_eq!(machine.step(), Some(overflow));
+        machine.patch(1, Instruction::new(ACC, -1));
+        assert_eq!(machine.run(), overflow);
+        machine.reset();
+        assert_eq!(machine.run().registers().accumulator, i64::MAX - 1);
+
+        let program = vec![Instruction::new(NOP, 0), Instruction::new(JMP, i64::MAX)];
+        assert_eq!(
+            Machine::new(program).run(),
+            ExecutionResult::Overflow(Registers {
+                accumulator: 0,
+                pc: 1
+            })
+        );
+    }
+}
@@ -0,0 +1,343 @@
+// Static analysis of handheld programs, without running them.
+//
+// Each instruction has a single successor, found by executing its opcode on its own address. This
+// assumes no opcode jumps depending on the accumulator, which holds for the boot code opcodes. With
+// a single successor per instruction the control-flow graph is a functional graph: following the
+// successors from any instruction either leaves the program or ends in exactly one loop.
+
+use super::{Instruction, Registers, JMP, NOP};
+
+/// Where the execution goes after an instruction.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Target {
+    Instruction(usize),
+    /// Right after the last instruction, so the program terminates.
+    Exit,
+    /// Any other address outside the program.
+    OutOfBounds(i64),
+    /// The address doesn't fit in 64 bits.
+    Overflow,
+}
+
+/// Replacing the instruction at the given position.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Patch {
+    pub position: usize,
+    pub instruction: Instruction,
+}
+
+impl Patch {
+    pub fn apply(&self, program: &mut [Instruction]) {
+        program[self.position] = self.instruction;
+    }
+}
+
+/// The instruction with nop and jmp swapped, or None if it is not one of them.
+pub fn switched(instruction: &Instruction) -> Option<Instruction> {
+    match instruction.opcode.name {
+        "nop" => Some(Instruction::new(JMP, instruction.argument)),
+        "jmp" => Some(Instruction::new(NOP, instruction.argument)),
+        _ => None,
+    }
+}
+
+/// Where the execution goes after running the instruction at the given position.
+pub(super) fn target(instruction: &Instruction, position: usize, len: usize) -> Target {
+    let registers = Registers {
+        accumulator: 0,
+        pc: position as i64,
+    };
+    let registers = match (instruction.opcode.execute)(registers, instruction.argument) {
+        Some(registers) => registers,
+        None => return Target::Overflow,
+    };
+    if registers.pc == len as i64 {
+        Target::Exit
+    } else if registers.pc < 0 || registers.pc > len as i64 {
+        Target::OutOfBounds(registers.pc)
+    } else {
+        Target::Instruction(registers.pc as usize)
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct ControlFlowGraph {
+    program: Vec<Instruction>,
+    successors: Vec<Target>,
+    predecessors: Vec<Vec<usize>>,
+    /// Number of instructions executed from each instruction until the program terminates, or
+    /// None if it never does.
+    distance_to_exit: Vec<Option<usize>>,
+}
+
+impl ControlFlowGraph {
+    pub fn new(program: &[Instruction]) -> ControlFlowGraph {
+        let successors: Vec<Target> = program
+            .iter()
+            .enumerate()
+            .map(|(position, instruction)| target(instruction, position, program.len()))
+            .collect();
+        let mut predecessors = vec![Vec::new(); program.len()];
+        let mut exits = Vec::new();
+        for (position, successor) in successors.iter().enumerate() {
+            match successor {
+                Target::Instruction(next) => predecessors[*next].push(position),
+                Target::Exit => exits.push(position),
+                Target::OutOfBounds(_) | Target::Overflow => {}
+            }
+        }
+        // Breadth-first search backwards from the exit.
+        let mut distance_to_exit = vec![None; program.len()];
+        for position in &exits {
+            distance_to_exit[*position] = Some(1);
+        }
+        let mut queue = exits;
+        let mut index = 0;
+        while index < queue.len() {
+            let position = queue[index];
+            let distance = distance_to_exit[position].unwrap();
+            for previous in &predecessors[position] {
+                if distance_to_exit[*previous].is_none() {
+                    distance_to_exit[*previous] = Some(distance + 1);
+                    queue.push(*previous);
+                }
+            }
+            index += 1;
+        }
+        ControlFlowGraph {
+            program: program.to_vec(),
+            successors,
+            predecessors,
+            distance_to_exit,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.program.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.program.is_empty()
+    }
+
+    pub fn successor(&self, position: usize) -> Target {
+        self.successors[position]
+    }
+
+    pub fn predecessors(&self, position: usize) -> &[usize] {
+        &self.predecessors[position]
+    }
+
+    fn target_terminates(&self, target: Target) -> bool {
+        match target {
+            Target::Instruction(position) => self.terminates(position),
+            Target::Exit => true,
+            Target::OutOfBounds(_) | Target::Overflow => false,
+        }
+    }
+
+    /// Whether running from the given instruction reaches the end of the program.
+    pub fn terminates(&self, position: usize) -> bool {
+        self.distance_to_exit[position].is_some()
+    }
+
+    /// Instructions that reach the end of the program, in order.
+    pub fn terminating_instructions(&self) -> Vec<usize> {
+        (0..self.len())
+            .filter(|position| self.terminates(*position))
+            .collect()
+    }
+
+    /// Instructions executed running the program from the first one, in execution order, and
+    /// where the execution goes after the last of them. If the program loops, the target is the
+    /// first repeated instruction.
+    pub fn execution_path(&self) -> (Vec<usize>, Target) {
+        let mut path = Vec::new();
+        let mut visited = vec![false; self.len()];
+        let mut next = if self.is_empty() {
+            Target::Exit
+        } else {
+            Target::Instruction(0)
+        };
+        while let Target::Instruction(position) = next {
+            if visited[position] {
+                break;
+            }
+            visited[position] = true;
+            path.push(position);
+            next = self.successors[position];
+        }
+        (path, next)
+    }
+
+    /// Every loop of the program, whether reachable from the first instruction or not. Each loop
+    /// starts at its lowest address, and loops are sorted by it.
+    pub fn loops(&self) -> Vec<Vec<usize>> {
+        // 0: not visited yet, 1: on the walk being followed, 2: done.
+        let mut state = vec![0_u8; self.len()];
+        let mut loops = Vec::new();
+        for start in 0..self.len() {
+            let mut walk = Vec::new();
+            let mut next = Target::Instruction(start);
+            while let Target::Instruction(position) = next {
+                match state[position] {
+                    0 => {
+                        state[position] = 1;
+                        walk.push(position);
+                        next = self.successors[position];
+                    }
+                    1 => {
+                        let begin = walk.iter().position(|p| *p == position).unwrap();
+                        let mut cycle = walk[begin..].to_vec();
+                        let lowest = (0..cycle.len()).min_by_key(|i| cycle[*i]).unwrap();
+                        cycle.rotate_left(lowest);
+                        loops.push(cycle);
+                        break;
+                    }
+                    _ => break,
+                }
+            }
+            for position in walk {
+                state[position] = 2;
+            }
+        }
+        loops.sort();
+        loops
+    }
+
+    /// Every nop/jmp swap that makes the program terminate. Only instructions executed by the
+    /// original program are considered, swapping any other one changes nothing.
+    pub fn terminating_patches(&self) -> Vec<Patch> {
+        let (path, _) = self.execution_path();
+        let mut patches: Vec<Patch> = path
+            .into_iter()
+            .filter_map(|position| {
+                let instruction = switched(&self.program[position])?;
+                let next = target(&instruction, position, self.len());
+                if !self.target_terminates(next) || self.passes_through(next, position) {
+                    return None;
+                }
+                Some(Patch {
+                    position,
+                    instruction,
+                })
+            })
+            .collect();
+        patches.sort_by_key(|patch| patch.position);
+        patches
+    }
+
+    /// Whether the execution from a terminating target goes through the given instruction.
+    fn passes_through(&self, target: Target, position: usize) -> bool {
+        let (mut current, distance) = match (target, self.distance_to_exit[position]) {
+            (Target::Instruction(current), Some(distance)) => (current, distance),
+            _ => return false,
+        };
+        // Distances decrease by one on each step, so only one instruction can match.
+        let mut remaining = self.distance_to_exit[current].unwrap();
+        while remaining > distance {
+            match self.successors[current] {
+                Target::Instruction(next) => current = next,
+                _ => return false,
+            }
+            remaining -= 1;
+        }
+        current == position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handheld::{ExecutionResult, InstructionSet, Machine};
+
+    fn parse(input_text: &str) -> Vec<Instruction> {
+        let lines: Vec<String> = input_text.lines().map(|line| line.to_string()).collect();
+        InstructionSet::boot_code().parse_program(&lines)
+    }
+
+    fn example_program() -> Vec<Instruction> {
+        parse("nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6")
+    }
+
+    #[test]
+    pub fn test_graph() {
+        let graph = ControlFlowGraph::new(&example_program());
+        assert_eq!(graph.successor(2), Target::Instruction(6));
+        assert_eq!(graph.successor(8), Target::Exit);
+        assert_eq!(graph.predecessors(3), &[7]);
+        assert_eq!(graph.terminating_instructions(), vec![8]);
+        assert_eq!(
+            graph.execution_path(),
+            (vec![0, 1, 2, 6, 7, 3, 4], Target::Instruction(1))
+        );
+        assert_eq!(graph.loops(), vec![vec![1, 2, 6, 7, 3, 4]]);
+    }
+
+    #[test]
+    pub fn test_terminating_patches() {
+        let program = example_program();
+        let patches = ControlFlowGraph::new(&program).terminating_patches();
+        assert_eq!(
+            patches,
+            vec![Patch {
+                position: 7,
+                instruction: Instruction::new(NOP, -4)
+            }]
+        );
+        let mut patched = program;
+        patches[0].apply(&mut patched);
+        assert_eq!(Machine::new(patched).run().registers().accumulator, 8);
+    }
+
+    #[test]
+    pub fn test_patches_matching_brute_force() {
+        let program = parse("nop +3\njmp +2\njmp +3\njmp -1\nacc +1\nnop -4\njmp -5");
+        let graph = ControlFlowGraph::new(&program);
+        let brute_force: Vec<usize> = (0..program.len())
+            .filter(|position| {
+                let mut patched = program.clone();
+                match switched(&program[*position]) {
+                    Some(instruction) => patched[*position] = instruction,
+                    None => return false,
+                }
+                matches!(Machine::new(patched).run(), ExecutionResult::Terminated(_))
+            })
+            .collect();
+        let positions: Vec<usize> = graph
+            .terminating_patches()
+            .iter()
+            .map(|patch| patch.position)
+            .collect();
+        assert_eq!(positions, brute_force);
+    }
+
+    #[test]
+    pub fn test_terminating_program() {
+        // Swapping the jmp makes the execution go through it again, as a nop, and loop.
+        let program = parse("jmp +2\njmp +2\njmp -1\nacc +1");
+        let graph = ControlFlowGraph::new(&program);
+        assert_eq!(graph.execution_path(), (vec![0, 2, 1, 3], Target::Exit));
+        assert_eq!(graph.terminating_instructions(), vec![0, 1, 2, 3]);
+        assert!(graph.loops().is_empty());
+        let positions: Vec<usize> = graph
+            .terminating_patches()
+            .iter()
+            .map(|patch| patch.position)
+            .collect();
+        assert_eq!(positions, vec![0, 2]);
+    }
+
+    #[test]
+    pub fn test_out_of_bounds_and_separate_loops() {
+        let program = parse("jmp +2\njmp +0\njmp -3\nnop +0\njmp -1");
+        let graph = ControlFlowGraph::new(&program);
+        assert_eq!(graph.successor(2), Target::OutOfBounds(-1));
+        assert_eq!(
+            graph.execution_path(),
+            (vec![0, 2], Target::OutOfBounds(-1))
+        );
+        assert_eq!(graph.loops(), vec![vec![1], vec![3, 4]]);
+        assert!(graph.terminating_instructions().is_empty());
+    }
+}
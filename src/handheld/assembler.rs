@@ -0,0 +1,261 @@
+// Assembler and disassembler for handheld programs.
+//
+// Assembly source has one instruction per line and can use labels and comments:
+//
+//     # Adds 3 to the accumulator forever.
+//     loop_start:
+//         acc +3
+//         jmp loop_start
+//
+// A label argument is lowered to the offset from the instruction to the label, so the program above
+// assembles to `acc +3` and `jmp -1`. Disassembled listings assemble back to the same program: the
+// address column they start with is accepted, and checked, by the assembler.
+
+use super::analysis::{target, Target};
+use super::{Instruction, InstructionSet};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Why a source can't be assembled. Lines are numbered from 1.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum AssemblyError {
+    BadSyntax(usize, String),
+    UnknownOpcode(usize, String),
+    BadArgument(usize, String),
+    UnknownLabel(usize, String),
+    DuplicateLabel(usize, String),
+    /// The line starts with an address other than the one of its instruction.
+    WrongAddress(usize, usize),
+}
+
+impl fmt::Display for AssemblyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssemblyError::BadSyntax(line, text) => {
+                write!(f, "line {}: can't parse {}", line, text)
+            }
+            AssemblyError::UnknownOpcode(line, name) => {
+                write!(f, "line {}: unknown opcode {}", line, name)
+            }
+            AssemblyError::BadArgument(line, argument) => {
+                write!(f, "line {}: bad argument {}", line, argument)
+            }
+            AssemblyError::UnknownLabel(line, label) => {
+                write!(f, "line {}: unknown label {}", line, label)
+            }
+            AssemblyError::DuplicateLabel(line, label) => {
+                write!(f, "line {}: label {} already defined", line, label)
+            }
+            AssemblyError::WrongAddress(line, address) => {
+                write!(f, "line {}: expected address {}", line, address)
+            }
+        }
+    }
+}
+
+/// A source line with an instruction, before resolving labels.
+struct SourceInstruction<'a> {
+    line: usize,
+    opcode: &'a str,
+    argument: &'a str,
+}
+
+/// Assembles the source into a program using the given opcodes.
+pub fn assemble(
+    source: &[String],
+    instruction_set: &InstructionSet,
+) -> Result<Vec<Instruction>, AssemblyError> {
+    lazy_static! {
+        static ref REGEX: Regex =
+            Regex::new(r"^\s*(?:(\d+)\s+)?(?:([A-Za-z_]\w*):)?\s*(?:([a-z]+)\s+([+\-]?\w+))?\s*$")
+                .unwrap();
+    }
+    // First pass: find the labels and the instructions.
+    let mut labels: HashMap<&str, usize> = HashMap::new();
+    let mut instructions: Vec<SourceInstruction> = Vec::new();
+    for (index, text) in source.iter().enumerate() {
+        let line = index + 1;
+        let code = text.split('#').next().unwrap();
+        let cap = REGEX
+            .captures(code)
+            .ok_or_else(|| AssemblyError::BadSyntax(line, text.trim().to_string()))?;
+        if let Some(label) = cap.get(2) {
+            if labels.insert(label.as_str(), instructions.len()).is_some() {
+                return Err(AssemblyError::DuplicateLabel(
+                    line,
+                    label.as_str().to_string(),
+                ));
+            }
+        }
+        if let Some(address) = cap.get(1) {
+            if address.as_str().parse::<usize>() != Ok(instructions.len()) || cap.get(3).is_none() {
+                return Err(AssemblyError::WrongAddress(line, instructions.len()));
+            }
+        }
+        if let (Some(opcode), Some(argument)) = (cap.get(3), cap.get(4)) {
+            instructions.push(SourceInstruction {
+                line,
+                opcode: opcode.as_str(),
+                argument: argument.as_str(),
+            });
+        }
+    }
+    // Second pass: resolve opcodes and arguments.
+    instructions
+        .iter()
+        .enumerate()
+        .map(|(position, source)| {
+            let opcode = instruction_set
+                .opcode(source.opcode)
+                .ok_or_else(|| AssemblyError::UnknownOpcode(source.line, source.opcode.into()))?;
+            let argument = if source
+                .argument
+                .starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            {
+                let label = labels.get(source.argument).ok_or_else(|| {
+                    AssemblyError::UnknownLabel(source.line, source.argument.into())
+                })?;
+                *label as i64 - position as i64
+            } else {
+                source
+                    .argument
+                    .parse::<i64>()
+                    .map_err(|_| AssemblyError::BadArgument(source.line, source.argument.into()))?
+            };
+            Ok(Instruction::new(opcode, argument))
+        })
+        .collect()
+}
+
+/// Assembles the source into the `nop/acc/jmp ±N` text format, one instruction per line.
+pub fn assemble_to_text(
+    source: &[String],
+    instruction_set: &InstructionSet,
+) -> Result<Vec<String>, AssemblyError> {
+    Ok(assemble(source, instruction_set)?
+        .iter()
+        .map(|instruction| instruction.to_string())
+        .collect())
+}
+
+/// Listing of the program with the address of each instruction and, for instructions not followed
+/// by the next one, where they go.
+pub fn disassemble(program: &[Instruction]) -> Vec<String> {
+    let width = program.len().to_string().len().max(4);
+    program
+        .iter()
+        .enumerate()
+        .map(|(position, instruction)| {
+            let line = format!("{:0width$}  {}", position, instruction, width = width);
+            let destination = match target(instruction, position, program.len()) {
+                Target::Instruction(next) if next == position + 1 => return line,
+                Target::Instruction(next) => format!("{:0width$}", next, width = width),
+                Target::Exit => "end".to_string(),
+                Target::OutOfBounds(pc) => format!("out of bounds ({})", pc),
+                Target::Overflow => "overflow".to_string(),
+            };
+            format!("{:<width$}  # -> {}", line, destination, width = width + 12)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handheld::{Machine, ACC, JMP, NOP};
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    pub fn test_assemble_labels_and_comments() {
+        let source = lines(
+            "# Example from the puzzle, with labels\n\
+             nop +0\n\
+             acc +1\n\
+             jmp middle   # skip ahead\n\
+             back: acc +3\n\
+             jmp -3\n\
+             \n\
+             acc -99\n\
+             middle:\n\
+             acc 1\n\
+             jmp back\n\
+             acc +6",
+        );
+        assert_eq!(
+            assemble_to_text(&source, &InstructionSet::boot_code()).unwrap(),
+            lines("nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6")
+        );
+    }
+
+    #[test]
+    pub fn test_assemble_errors() {
+        let instruction_set = InstructionSet::boot_code();
+        let assemble_line = |text: &str| assemble(&lines(text), &instruction_set);
+        assert_eq!(
+            assemble_line("nop +0\nmul +2"),
+            Err(AssemblyError::UnknownOpcode(2, "mul".to_string()))
+        );
+        assert_eq!(
+            assemble_line("acc +2x"),
+            Err(AssemblyError::BadArgument(1, "+2x".to_string()))
+        );
+        assert_eq!(
+            assemble_line("jmp start"),
+            Err(AssemblyError::UnknownLabel(1, "start".to_string()))
+        );
+        assert_eq!(
+            assemble_line("acc +1 +2"),
+            Err(AssemblyError::BadSyntax(1, "acc +1 +2".to_string()))
+        );
+        assert_eq!(
+            assemble_line("a: nop +0\na: jmp a"),
+            Err(AssemblyError::DuplicateLabel(2, "a".to_string()))
+        );
+        assert_eq!(
+            assemble_line("0000 nop +0\n0002 nop +0"),
+            Err(AssemblyError::WrongAddress(2, 1))
+        );
+        assert_eq!(
+            AssemblyError::UnknownLabel(3, "start".to_string()).to_string(),
+            "line 3: unknown label start"
+        );
+    }
+
+    #[test]
+    pub fn test_disassemble() {
+        let program = vec![
+            Instruction::new(NOP, 0),
+            Instruction::new(ACC, 1),
+            Instruction::new(JMP, 2),
+            Instruction::new(JMP, -4),
+            Instruction::new(JMP, 1),
+        ];
+        assert_eq!(
+            disassemble(&program),
+            vec![
+                "0000  nop +0",
+                "0001  acc +1",
+                "0002  jmp +2      # -> 0004",
+                "0003  jmp -4      # -> out of bounds (-1)",
+                "0004  jmp +1      # -> end",
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_round_trip() {
+        let instruction_set = InstructionSet::boot_code();
+        let text = lines("nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6");
+        let program = instruction_set.parse_program(&text);
+        let listing = disassemble(&program);
+        let assembled = assemble(&listing, &instruction_set).unwrap();
+        assert_eq!(assembled, program);
+        assert_eq!(assemble_to_text(&listing, &instruction_set).unwrap(), text);
+        assert_eq!(Machine::new(assembled).run().registers().accumulator, 5);
+    }
+}
@@ -0,0 +1,264 @@
+// Tracing and debugging on top of the handheld machine: every executed instruction is recorded, and
+// execution can be paused on breakpoints and resumed step by step.
+
+use super::{ExecutionResult, Instruction, Machine, Registers};
+
+/// An executed instruction, with the registers right before and right after running it.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct TraceEntry {
+    pub step: usize,
+    pub instruction: Instruction,
+    pub before: Registers,
+    pub after: Registers,
+}
+
+/// Conditions to pause on, checked before executing each instruction.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Breakpoint {
+    Pc(i64),
+    AccumulatorEquals(i64),
+    AccumulatorAbove(i64),
+    AccumulatorBelow(i64),
+}
+
+impl Breakpoint {
+    pub fn matches(&self, registers: &Registers) -> bool {
+        match *self {
+            Breakpoint::Pc(pc) => registers.pc == pc,
+            Breakpoint::AccumulatorEquals(value) => registers.accumulator == value,
+            Breakpoint::AccumulatorAbove(value) => registers.accumulator > value,
+            Breakpoint::AccumulatorBelow(value) => registers.accumulator < value,
+        }
+    }
+}
+
+/// Why the tracer gave control back.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Pause {
+    /// About to execute an instruction matching the breakpoint.
+    Breakpoint(Breakpoint),
+    /// The machine can't run anymore.
+    Halted(ExecutionResult),
+}
+
+/// Runs a machine recording its trace.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Tracer {
+    machine: Machine,
+    breakpoints: Vec<Breakpoint>,
+    trace: Vec<TraceEntry>,
+    /// Whether the last `resume` paused on a breakpoint, and nothing was executed since.
+    at_breakpoint: bool,
+}
+
+impl Tracer {
+    pub fn new(machine: Machine) -> Tracer {
+        Tracer {
+            machine,
+            breakpoints: Vec::new(),
+            trace: Vec::new(),
+            at_breakpoint: false,
+        }
+    }
+
+    pub fn with_breakpoint(mut self, breakpoint: Breakpoint) -> Tracer {
+        self.add_breakpoint(breakpoint);
+        self
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        if !self.breakpoints.contains(&breakpoint) {
+            self.breakpoints.push(breakpoint);
+        }
+    }
+
+    pub fn remove_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.retain(|existing| *existing != breakpoint);
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    pub fn registers(&self) -> Registers {
+        self.machine.registers()
+    }
+
+    /// Executed instructions, oldest first.
+    pub fn trace(&self) -> &[TraceEntry] {
+        &self.trace
+    }
+
+    /// Executes a single instruction ignoring breakpoints, same as `Machine::step`.
+    pub fn step(&mut self) -> Option<ExecutionResult> {
+        self.at_breakpoint = false;
+        let before = self.machine.registers();
+        let instruction = self.machine.current_instruction();
+        let result = self.machine.step();
+        if result.is_none() {
+            self.trace.push(TraceEntry {
+                step: self.machine.steps() - 1,
+                instruction: instruction.unwrap(),
+                before,
+                after: self.machine.registers(),
+            });
+        }
+        result
+    }
+
+    /// Runs until a breakpoint matches or the machine halts. Breakpoints are checked before
+    /// executing each instruction, including the current one, unless the tracer is paused on a
+    /// breakpoint: then the current instruction is executed first, so it doesn't pause there again.
+    pub fn resume(&mut self) -> Pause {
+        let mut check_breakpoints = !self.at_breakpoint;
+        loop {
+            if let Some(result) = self.machine.halted() {
+                return Pause::Halted(result);
+            }
+            if check_breakpoints {
+                let registers = self.machine.registers();
+                if let Some(breakpoint) = self
+                    .breakpoints
+                    .iter()
+                    .find(|breakpoint| breakpoint.matches(&registers))
+                {
+                    self.at_breakpoint = true;
+                    return Pause::Breakpoint(*breakpoint);
+                }
+            }
+            check_breakpoints = true;
+            self.step();
+        }
+    }
+
+    /// Runs ignoring breakpoints until the machine halts.
+    pub fn run(&mut self) -> ExecutionResult {
+        loop {
+            if let Some(result) = self.step() {
+                return result;
+            }
+        }
+    }
+
+    /// One line per executed instruction: step, address, instruction and accumulator change.
+    pub fn dump(&self) -> Vec<String> {
+        self.trace
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{:>6}  {:04}  {:<10}  acc {} -> {}",
+                    entry.step,
+                    entry.before.pc,
+                    entry.instruction.to_string(),
+                    entry.before.accumulator,
+                    entry.after.accumulator
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handheld::InstructionSet;
+
+    fn example_machine() -> Machine {
+        let input_text = "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6";
+        let lines: Vec<String> = input_text.lines().map(|line| line.to_string()).collect();
+        Machine::new(InstructionSet::boot_code().parse_program(&lines))
+    }
+
+    #[test]
+    pub fn test_trace_until_loop() {
+        let mut tracer = Tracer::new(example_machine());
+        assert_eq!(
+            tracer.run(),
+            ExecutionResult::InfiniteLoop(Registers {
+                accumulator: 5,
+                pc: 1
+            })
+        );
+        let pcs: Vec<i64> = tracer.trace().iter().map(|entry| entry.before.pc).collect();
+        assert_eq!(pcs, vec![0, 1, 2, 6, 7, 3, 4]);
+        assert_eq!(
+            tracer.dump(),
+            vec![
+                "     0  0000  nop +0      acc 0 -> 0",
+                "     1  0001  acc +1      acc 0 -> 1",
+                "     2  0002  jmp +4      acc 1 -> 1",
+                "     3  0006  acc +1      acc 1 -> 2",
+                "     4  0007  jmp -4      acc 2 -> 2",
+                "     5  0003  acc +3      acc 2 -> 5",
+                "     6  0004  jmp -3      acc 5 -> 5",
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_breakpoints() {
+        let mut tracer = Tracer::new(example_machine())
+            .with_breakpoint(Breakpoint::Pc(7))
+            .with_breakpoint(Breakpoint::AccumulatorAbove(4));
+        assert_eq!(tracer.resume(), Pause::Breakpoint(Breakpoint::Pc(7)));
+        assert_eq!(
+            tracer.registers(),
+            Registers {
+                accumulator: 2,
+                pc: 7
+            }
+        );
+        assert_eq!(
+            tracer.resume(),
+            Pause::Breakpoint(Breakpoint::AccumulatorAbove(4))
+        );
+        assert_eq!(tracer.registers().pc, 4);
+        assert_eq!(tracer.step(), None);
+
+        tracer.remove_breakpoint(Breakpoint::AccumulatorAbove(4));
+        assert_eq!(tracer.breakpoints(), &[Breakpoint::Pc(7)]);
+        assert_eq!(
+            tracer.resume(),
+            Pause::Halted(ExecutionResult::InfiniteLoop(Registers {
+                accumulator: 5,
+                pc: 1
+            }))
+        );
+        assert_eq!(tracer.trace().len(), 7);
+        assert_eq!(tracer.step().map(|result| result.registers().pc), Some(1));
+        assert_eq!(tracer.trace().len(), 7);
+    }
+
+    #[test]
+    pub fn test_breakpoint_on_current_instruction() {
+        let mut tracer = Tracer::new(example_machine()).with_breakpoint(Breakpoint::Pc(0));
+        assert_eq!(tracer.resume(), Pause::Breakpoint(Breakpoint::Pc(0)));
+        assert!(tracer.trace().is_empty());
+
+        // Paused right after stepping onto a breakpoint.
+        tracer.add_breakpoint(Breakpoint::Pc(2));
+        assert_eq!(tracer.resume(), Pause::Breakpoint(Breakpoint::Pc(2)));
+        assert_eq!(tracer.trace().len(), 2);
+        tracer.add_breakpoint(Breakpoint::Pc(6));
+        assert_eq!(tracer.step(), None);
+        assert_eq!(tracer.resume(), Pause::Breakpoint(Breakpoint::Pc(6)));
+        assert_eq!(tracer.trace().len(), 3);
+    }
+
+    #[test]
+    pub fn test_accumulator_breakpoints() {
+        assert!(Breakpoint::AccumulatorEquals(3).matches(&Registers {
+            accumulator: 3,
+            pc: 0
+        }));
+        assert!(Breakpoint::AccumulatorBelow(0).matches(&Registers {
+            accumulator: -1,
+            pc: 0
+        }));
+        assert!(!Breakpoint::AccumulatorBelow(0).matches(&Registers::default()));
+    }
+}
@@ -1,3 +1,4 @@
+use std::io::{BufRead, BufReader};
 use std::str::FromStr;
 
 pub fn read_all(file_name: &str) -> Vec<String> {
@@ -17,3 +18,15 @@ pub fn read_all_as<T: FromStr>(file_name: &str) -> Vec<T> {
         })
         .collect()
 }
+
+/// Parses the file line by line as it is read, without loading it whole.
+pub fn stream_as<T: FromStr>(file_name: &str) -> impl Iterator<Item = T> {
+    let file = std::fs::File::open(file_name).expect("file not found!");
+    BufReader::new(file).lines().map(|line| {
+        let line = line.expect("Failed to read");
+        match line.parse::<T>() {
+            Ok(n) => n,
+            Err(_) => panic!("Failed to parse"),
+        }
+    })
+}
@@ -1,3 +1,4 @@
+pub mod big_uint;
 pub mod day1;
 pub mod day10;
 pub mod day11;
@@ -23,7 +24,10 @@ pub mod day6;
 pub mod day7;
 pub mod day8;
 pub mod day9;
+pub mod handheld;
 pub mod input_utils;
+pub mod number_theory;
+pub mod output_utils;
 
 /// Test all previous results stay stable.
 #[cfg(test)]
@@ -58,6 +62,14 @@ mod tests {
             day3::calculate_slopes_cost_multiplied(&day3_input),
             2224913600
         );
+        assert_eq!(
+            day3::calculate_cut_trees_for_cheap_toboggan_packed(&day3_input),
+            259
+        );
+        assert_eq!(
+            day3::calculate_slopes_cost_multiplied_packed(&day3_input),
+            2224913600
+        );
     }
 
     #[test]
@@ -65,6 +77,8 @@ mod tests {
         let day4_input = input_utils::read_all("inputs/day4");
         assert_eq!(day4::count_passwords_with_all_fields(&day4_input), 235);
         assert_eq!(day4::count_valid_passwords(&day4_input), 194);
+        let day4_schema = day4::Schema::from_file("inputs/day4_schema");
+        assert_eq!(day4::count_valid_passports(&day4_input, &day4_schema), 194);
     }
 
     #[test]
@@ -91,7 +105,14 @@ mod tests {
     pub fn test_day7() {
         let day7_input = input_utils::read_all("inputs/day7");
         assert_eq!(day7::count_bags_containing_shiny_gold(&day7_input), 326);
-        assert_eq!(day7::count_bags_inside_shiny_gold(&day7_input), 5635);
+        assert_eq!(day7::count_bags_inside_shiny_gold(&day7_input), Ok(5635));
+        let day7_graph = day7::BagGraph::parse(&day7_input);
+        assert_eq!(
+            day7_graph
+                .total_contents_big("shiny gold")
+                .map(|bags| bags.to_string()),
+            Ok("5635".to_string())
+        );
     }
 
     #[test]
@@ -108,7 +129,7 @@ mod tests {
     #[test]
     pub fn test_day9() {
         let day9_input = input_utils::read_all("inputs/day9");
-        assert_eq!(day9::find_first_invalid(&day9_input, 25), 29221323);
+        assert_eq!(day9::find_first_invalid(&day9_input, 25), Some(29221323));
         assert_eq!(day9::find_vulnerability(&day9_input, 25), 4389369);
     }
 
@@ -120,6 +141,8 @@ mod tests {
             day10::count_total_combinations(&day10_input),
             10578455953408
         );
+        let chain = day10::AdapterChain::parse(&day10_input, 1, 3, 3);
+        assert_eq!(chain.count_arrangements_big().to_string(), "10578455953408");
     }
 
     #[test]
@@ -1,6 +1,12 @@
 use advent_of_code2020_rust::*;
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        run_command(&args);
+        return;
+    }
+
     let day1_input = input_utils::read_all_as::<u32>("inputs/day1");
     println!("Day 1 - Part 1: {}", day1::part1(&day1_input));
     println!("Day 1 - Part 2: {}", day1::part2(&day1_input));
@@ -32,7 +38,7 @@ fn main() {
     );
     println!(
         "Day 4 - Part 2: {}",
-        day4::count_valid_passwords(&day4_input)
+        day4::count_valid_passports(&day4_input, &day4::Schema::from_file("inputs/day4_schema"))
     );
 
     let day5_input = input_utils::read_all("inputs/day5");
@@ -57,6 +63,7 @@ fn main() {
     println!(
         "Day 7 - Part 2: {}",
         day7::count_bags_inside_shiny_gold(&day7_input)
+            .unwrap_or_else(|error| panic!("Unexpected bag rules: {}", error))
     );
 
     let day8_input = input_utils::read_all("inputs/day8");
@@ -76,7 +83,7 @@ fn main() {
     let day9_input = input_utils::read_all("inputs/day9");
     println!(
         "Day 9 - Part 1: {}",
-        day9::find_first_invalid(&day9_input, 25)
+        day9::find_first_invalid(&day9_input, 25).unwrap()
     );
     println!(
         "Day 9 - Part 2: {}",
@@ -152,3 +159,199 @@ fn main() {
 
     println!("Day 25: {}", day25::part1(18499292, 8790390));
 }
+
+/// Extra tools on top of the puzzle answers, run as `cargo run -- <command> [arguments...]`.
+fn run_command(args: &[String]) {
+    let arg = |index: usize, default: &'static str| -> String {
+        args.get(index)
+            .cloned()
+            .unwrap_or_else(|| default.to_string())
+    };
+    match args[0].as_str() {
+        "day4-rejections" => {
+            let input = input_utils::read_all(&arg(1, "inputs/day4"));
+            let schema = day4::Schema::from_file(&arg(2, "inputs/day4_schema"));
+            for line in day4::rejection_summary(&input, &schema) {
+                println!("{}", line);
+            }
+        }
+        "day4-normalise" => {
+            let format = match arg(1, "canonical").as_str() {
+                "canonical" => day4::BatchFormat::Canonical,
+                "json" => day4::BatchFormat::Json,
+                format => panic!("Unexpected format {}", format),
+            };
+            let selection = match arg(2, "all").as_str() {
+                "all" => day4::Selection::All,
+                "valid" => day4::Selection::Valid,
+                "invalid" => day4::Selection::Invalid,
+                selection => panic!("Unexpected selection {}", selection),
+            };
+            let input = input_utils::read_all(&arg(3, "inputs/day4"));
+            let schema = day4::Schema::from_file(&arg(4, "inputs/day4_schema"));
+            for line in day4::normalise_passports(&input, &schema, format, selection) {
+                println!("{}", line);
+            }
+        }
+        "day5-seats" => {
+            let input = input_utils::read_all(&arg(1, "inputs/day5"));
+            let seat_map = day5::SeatMap::parse(&input, day5::Geometry::PUZZLE);
+            for line in seat_map.render() {
+                println!("{}", line);
+            }
+            println!(
+                "Empty seats between occupied ones: {:?}",
+                seat_map.empty_seats_between_occupied()
+            );
+            println!("Missing front rows: {:?}", seat_map.missing_front_rows());
+            println!("Missing back rows: {:?}", seat_map.missing_back_rows());
+            for (boarding_pass, passes) in seat_map.duplicated_seats() {
+                println!(
+                    "Duplicated seat {} (ID {}): {} boarding passes",
+                    boarding_pass,
+                    boarding_pass.seat_id(),
+                    passes
+                );
+            }
+        }
+        "day7-validate" => {
+            let graph = day7::BagGraph::parse(&input_utils::read_all(&arg(1, "inputs/day7")));
+            let color = arg(2, "shiny gold");
+            let validation = graph.validate(&color);
+            for cycle in &validation.cycles {
+                println!("Cycle: {}", cycle.join(" -> "));
+            }
+            for (outer, inner) in &validation.dangling {
+                println!("No rule for {} bags, referenced by {} bags", inner, outer);
+            }
+            for unreachable in &validation.unreachable {
+                println!("Unrelated to {}: {}", color, unreachable);
+            }
+            println!(
+                "Rules are {}",
+                if validation.is_valid() {
+                    "valid"
+                } else {
+                    "invalid"
+                }
+            );
+        }
+        "day7-export" => {
+            let color = arg(3, "shiny gold");
+            let subgraph = match arg(2, "all").as_str() {
+                "all" => day7::Subgraph::All,
+                "from" => day7::Subgraph::From(color.clone()),
+                "to" => day7::Subgraph::To(color.clone()),
+                subgraph => panic!("Unexpected subgraph {}", subgraph),
+            };
+            let graph = day7::BagGraph::parse(&input_utils::read_all(&arg(4, "inputs/day7")));
+            let lines = match arg(1, "dot").as_str() {
+                "dot" => graph.to_dot(&subgraph, Some(&color)),
+                "json" => graph.to_json(&subgraph),
+                format => panic!("Unexpected format {}", format),
+            };
+            match lines {
+                Ok(lines) => lines.iter().for_each(|line| println!("{}", line)),
+                Err(error) => eprintln!("Can't export the bag rules: {}", error),
+            }
+        }
+        "day8-trace" => {
+            let input = input_utils::read_all(&arg(1, "inputs/day8"));
+            let step_limit = arg(2, "1000")
+                .parse::<usize>()
+                .unwrap_or_else(|_| panic!("Unexpected step limit {}", arg(2, "")));
+            let program = handheld::InstructionSet::boot_code().parse_program(&input);
+            let machine = handheld::Machine::new(program).with_step_limit(step_limit);
+            let mut tracer = handheld::trace::Tracer::new(machine);
+            let result = tracer.run();
+            for line in tracer.dump() {
+                println!("{}", line);
+            }
+            println!("{:?}", result);
+        }
+        "day8-disassemble" => {
+            let input = input_utils::read_all(&arg(1, "inputs/day8"));
+            let program = handheld::InstructionSet::boot_code().parse_program(&input);
+            for line in handheld::assembler::disassemble(&program) {
+                println!("{}", line);
+            }
+        }
+        "day8-assemble" => {
+            let source = input_utils::read_all(&arg(1, "inputs/day8"));
+            let instruction_set = handheld::InstructionSet::boot_code();
+            match handheld::assembler::assemble_to_text(&source, &instruction_set) {
+                Ok(lines) => lines.iter().for_each(|line| println!("{}", line)),
+                Err(error) => eprintln!("Can't assemble the program: {}", error),
+            }
+        }
+        "day9-invalid" => {
+            let preamble = arg(2, "25")
+                .parse::<usize>()
+                .unwrap_or_else(|_| panic!("Unexpected preamble {}", arg(2, "")));
+            let numbers = input_utils::stream_as::<i64>(&arg(1, "inputs/day9"));
+            for invalid in day9::invalid_numbers(numbers, preamble) {
+                println!("{}: {}", invalid.index, invalid.value);
+            }
+        }
+        "day11-simulate" => {
+            let input = input_utils::read_all(&arg(1, "inputs/day11"));
+            let rules = match day11::SeatingRules::preset(&arg(2, "part2")) {
+                Some(rules) => rules,
+                None => {
+                    let threshold = |index: usize, default: &'static str| {
+                        arg(index, default).parse::<usize>().unwrap_or_else(|_| {
+                            panic!("Unexpected threshold {}", arg(index, default))
+                        })
+                    };
+                    day11::SeatingRules {
+                        neighbourhood: day11::Neighbourhood::parse(&arg(2, "")),
+                        birth_threshold: threshold(3, "0"),
+                        leave_threshold: threshold(4, "4"),
+                    }
+                }
+            };
+            let mut simulation = day11::SeatSimulation::new(&input, &rules);
+            let result = simulation.run_until_stable(day11::DEFAULT_ROUND_LIMIT);
+            for line in simulation.render() {
+                println!("{}", line);
+            }
+            match result {
+                Ok(round) => println!("Stable after round {}", round),
+                Err(error) => println!("Not stable: {}", error),
+            }
+            println!("Occupied seats: {}", simulation.count_occupied());
+        }
+        "day12-navigate" | "day12-svg" => {
+            let input = input_utils::read_all(&arg(1, "inputs/day12"));
+            let mut ship = match arg(2, "heading").as_str() {
+                "heading" => day12::Ship::heading(),
+                "waypoint" => day12::Ship::with_waypoint(),
+                mode => panic!("Unexpected mode {}", mode),
+            };
+            let syntax = match arg(3, "standard").as_str() {
+                "standard" => day12::Syntax::Standard,
+                "extended" => day12::Syntax::Extended,
+                syntax => panic!("Unexpected syntax {}", syntax),
+            };
+            let trajectory = match ship.record(&day12::parse_instructions(&input, syntax)) {
+                Ok(trajectory) => trajectory,
+                Err(error) => {
+                    println!("Can't navigate: {}", error);
+                    return;
+                }
+            };
+            if args[0] == "day12-svg" {
+                println!("{}", trajectory.to_svg());
+                return;
+            }
+            println!("Position: {}", ship.position);
+            println!("Waypoint: {}", ship.waypoint);
+            println!("Manhattan distance: {}", ship.distance());
+            let bounds = trajectory.bounding_box();
+            println!("Bounding box: {} to {}", bounds.min, bounds.max);
+            println!("Distance travelled: {}", trajectory.distance_travelled());
+            println!("Furthest point: {}", trajectory.furthest_point());
+        }
+        command => panic!("Unexpected command {}", command),
+    }
+}
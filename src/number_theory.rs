@@ -0,0 +1,233 @@
+// Number theory on 64 bit integers: gcd, lcm, modular inverses and systems of congruences.
+//
+// Intermediate products are computed with 128 bits, so the only overflow possible is a result
+// that doesn't fit in 64 bits, which is reported instead of wrapping around.
+
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Greatest common divisor. Unsigned, as gcd(i64::MIN, 0) is 2^63. gcd(0, 0) = 0.
+pub fn gcd(a: i64, b: i64) -> u64 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        let remainder = a % b;
+        a = b;
+        b = remainder;
+    }
+    a
+}
+
+/// Least common multiple, always non negative, or None if it doesn't fit in 64 bits.
+pub fn lcm(a: i64, b: i64) -> Option<i64> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+    let lcm = (a.unsigned_abs() / gcd(a, b)) as u128 * b.unsigned_abs() as u128;
+    i64::try_from(lcm).ok()
+}
+
+/// The gcd `g` of `a` and `b`, with coefficients `x` and `y` such that `a * x + b * y = g`, or
+/// None if the gcd is 2^63, which only happens for `i64::MIN` with itself or 0.
+pub fn extended_gcd(a: i64, b: i64) -> Option<(i64, i64, i64)> {
+    let (mut old_r, mut r) = (a as i128, b as i128);
+    let (mut old_x, mut x) = (1_i128, 0_i128);
+    let (mut old_y, mut y) = (0_i128, 1_i128);
+    while r != 0 {
+        let quotient = old_r / r;
+        old_r -= quotient * r;
+        old_x -= quotient * x;
+        old_y -= quotient * y;
+        std::mem::swap(&mut old_r, &mut r);
+        std::mem::swap(&mut old_x, &mut x);
+        std::mem::swap(&mut old_y, &mut y);
+    }
+    if old_r < 0 {
+        old_r = -old_r;
+        old_x = -old_x;
+        old_y = -old_y;
+    }
+    Some((
+        i64::try_from(old_r).ok()?,
+        i64::try_from(old_x).ok()?,
+        i64::try_from(old_y).ok()?,
+    ))
+}
+
+/// The `x` in `0..modulus` such that `a * x` is 1 modulo `modulus`, if `a` and `modulus` are
+/// coprime.
+pub fn mod_inverse(a: i64, modulus: i64) -> Option<i64> {
+    let (g, x, _) = extended_gcd(a, modulus)?;
+    if g == 1 {
+        Some(x.rem_euclid(modulus))
+    } else {
+        None
+    }
+}
+
+/// The numbers equal to `residue` modulo `modulus`. The residue is kept in `0..modulus`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Congruence {
+    pub residue: i64,
+    pub modulus: i64,
+}
+
+impl Congruence {
+    pub fn new(residue: i64, modulus: i64) -> Congruence {
+        if modulus <= 0 {
+            panic!("Unexpected modulus {}", modulus);
+        }
+        Congruence {
+            residue: residue.rem_euclid(modulus),
+            modulus,
+        }
+    }
+
+    pub fn contains(&self, number: i64) -> bool {
+        number.rem_euclid(self.modulus) == self.residue
+    }
+
+    /// The numbers satisfying both congruences, modulo the lcm of both moduli. The moduli don't
+    /// need to be coprime, but then the residues have to agree modulo their gcd.
+    pub fn merge(&self, other: &Congruence) -> Result<Congruence, CongruenceError> {
+        // Both moduli are positive, so their gcd fits in 64 bits.
+        let (g, x, _) = extended_gcd(self.modulus, other.modulus).unwrap();
+        let difference = other.residue - self.residue;
+        if difference % g != 0 {
+            return Err(CongruenceError::Inconsistent(*self, *other));
+        }
+        let modulus = lcm(self.modulus, other.modulus).ok_or(CongruenceError::Overflow)?;
+        // self.residue + self.modulus * k, with k solving
+        // (self.modulus / g) * k = difference / g (mod other.modulus / g).
+        let k = ((difference / g) as i128 * x as i128).rem_euclid((other.modulus / g) as i128);
+        let residue = self.residue as i128 + self.modulus as i128 * k;
+        Ok(Congruence::new(residue as i64, modulus))
+    }
+}
+
+/// E.g. `3 mod 7`.
+impl fmt::Display for Congruence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} mod {}", self.residue, self.modulus)
+    }
+}
+
+/// Why a system of congruences can't be solved.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum CongruenceError {
+    /// No number satisfies both congruences.
+    Inconsistent(Congruence, Congruence),
+    /// The lcm of the moduli doesn't fit in 64 bits.
+    Overflow,
+}
+
+impl fmt::Display for CongruenceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CongruenceError::Inconsistent(a, b) => write!(f, "{} contradicts {}", b, a),
+            CongruenceError::Overflow => write!(f, "moduli lcm overflows 64 bits"),
+        }
+    }
+}
+
+/// Generalised Chinese remainder theorem: the numbers satisfying all the congruences, as a single
+/// congruence modulo the lcm of the moduli. Without congruences every number is a solution.
+pub fn chinese_remainder(congruences: &[Congruence]) -> Result<Congruence, CongruenceError> {
+    congruences
+        .iter()
+        .try_fold(Congruence::new(0, 1), |solution, congruence| {
+            solution.merge(congruence)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_gcd_and_lcm() {
+        assert_eq!(gcd(12, -18), 6);
+        assert_eq!(gcd(0, 5), 5);
+        assert_eq!(gcd(0, 0), 0);
+        assert_eq!(lcm(4, 6), Some(12));
+        assert_eq!(lcm(-4, 6), Some(12));
+        assert_eq!(lcm(i64::MAX, 2), None);
+        assert_eq!(lcm(1 << 62, 1 << 40), Some(1 << 62));
+
+        assert_eq!(gcd(i64::MIN, 0), 1 << 63);
+        assert_eq!(gcd(i64::MIN, i64::MIN), 1 << 63);
+        assert_eq!(gcd(i64::MIN, 6), 2);
+        assert_eq!(lcm(i64::MIN, 3), None);
+        assert_eq!(lcm(i64::MIN, 1), None);
+        assert_eq!(lcm(i64::MIN, 0), Some(0));
+        assert_eq!(lcm(1 << 62, -2), Some(1 << 62));
+    }
+
+    #[test]
+    pub fn test_extended_gcd() {
+        for (a, b) in [
+            (240, 46),
+            (-240, 46),
+            (17, 0),
+            (0, -9),
+            (i64::MAX, i64::MAX - 1),
+            (i64::MIN, 6),
+            (i64::MIN, i64::MAX),
+            (1, i64::MIN),
+        ]
+        .iter()
+        {
+            let (g, x, y) = extended_gcd(*a, *b).unwrap();
+            assert_eq!(g as u64, gcd(*a, *b));
+            assert_eq!(*a as i128 * x as i128 + *b as i128 * y as i128, g as i128);
+        }
+        assert_eq!(mod_inverse(3, 7), Some(5));
+        assert_eq!(mod_inverse(-3, 7), Some(2));
+        assert_eq!(mod_inverse(4, 6), None);
+        assert_eq!(extended_gcd(i64::MIN, 0), None);
+        assert_eq!(extended_gcd(i64::MIN, i64::MIN), None);
+        assert_eq!(extended_gcd(0, 0), Some((0, 1, 0)));
+    }
+
+    #[test]
+    pub fn test_chinese_remainder() {
+        let coprime = [
+            Congruence::new(2, 3),
+            Congruence::new(3, 5),
+            Congruence::new(2, 7),
+        ];
+        assert_eq!(chinese_remainder(&coprime), Ok(Congruence::new(23, 105)));
+
+        let not_coprime = [Congruence::new(3, 4), Congruence::new(5, 6)];
+        let solution = chinese_remainder(&not_coprime).unwrap();
+        assert_eq!(solution, Congruence::new(11, 12));
+        assert!(not_coprime.iter().all(|c| c.contains(solution.residue)));
+
+        let inconsistent = [Congruence::new(0, 4), Congruence::new(5, 6)];
+        assert_eq!(
+            chinese_remainder(&inconsistent),
+            Err(CongruenceError::Inconsistent(
+                Congruence::new(0, 4),
+                Congruence::new(5, 6)
+            ))
+        );
+        assert_eq!(
+            chinese_remainder(&inconsistent).unwrap_err().to_string(),
+            "5 mod 6 contradicts 0 mod 4"
+        );
+
+        assert_eq!(chinese_remainder(&[]), Ok(Congruence::new(0, 1)));
+    }
+
+    #[test]
+    pub fn test_chinese_remainder_large_moduli() {
+        // A modulus close to 2^61, the products in the merge need more than 64 bits.
+        let a = Congruence::new(-1, (1 << 61) - 1);
+        let b = Congruence::new(12345, 3);
+        let solution = chinese_remainder(&[a, b]).unwrap();
+        assert!(a.contains(solution.residue) && b.contains(solution.residue));
+        assert_eq!(solution.modulus, 3 * ((1 << 61) - 1));
+
+        let c = Congruence::new(0, (1 << 62) - 57);
+        assert_eq!(chinese_remainder(&[a, c]), Err(CongruenceError::Overflow));
+    }
+}
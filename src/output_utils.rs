@@ -0,0 +1,41 @@
+/// Quotes a string as a JSON string literal, escaping what needs escaping.
+pub fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for char in value.chars() {
+        match char {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            char if (char as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", char as u32)),
+            char => quoted.push(char),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Quotes a string as a Graphviz DOT identifier.
+pub fn dot_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_json_string() {
+        assert_eq!(json_string("shiny gold"), "\"shiny gold\"");
+        assert_eq!(json_string("#123abc"), "\"#123abc\"");
+        assert_eq!(json_string("a\"b\\c\nd\u{1}"), "\"a\\\"b\\\\c\\nd\\u0001\"");
+    }
+
+    #[test]
+    pub fn test_dot_string() {
+        assert_eq!(dot_string("shiny gold"), "\"shiny gold\"");
+        assert_eq!(dot_string("a\"b\\c"), "\"a\\\"b\\\\c\"");
+    }
+}